@@ -1,11 +1,45 @@
+mod atomico;
+mod backend;
+mod bloqueio;
+mod colacao;
+mod consulta;
+mod csv;
+mod documento;
+mod email;
+mod excecoes;
+mod historico;
+mod jsonl;
+mod telefone;
+mod validacao;
+
 use ext_php_rs::{
     prelude::*,
-    exception::PhpException,
+    boxed::ZBox,
+    types::ZendHashTable,
 };
-use std::fs::{File, OpenOptions};
-use std::io::{Write, BufReader, BufRead};
-use std::path::Path;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use backend::{
+    conflito_versao, mensagem_email_duplicado, nao_encontrada, normalizar_email, verificar_emails_unicos, ArquivoBackend, FluxoPessoas,
+    MemoriaBackend, Operacao, StorageBackend, LIMITE_COMPACTACAO_PADRAO,
+};
+use colacao::ChaveColacao;
+pub use consulta::Consulta;
+use csv::FormatoCsv;
+use documento::Documento;
+use email::EnderecoEmail;
+pub use excecoes::{
+    ArmazenamentoException, BloqueioException, ConflitoException, DuplicidadeException,
+    NaoEncontradoException, ValidacaoException, WeadException,
+};
+pub use historico::Alteracao;
+use historico::estado_em;
+use telefone::{FormatoTelefone, Telefone};
+pub use validacao::RegrasValidacao;
 
 // ============================================================================
 // INTERFACE: InterfacePersistivel
@@ -72,10 +106,11 @@ impl EntidadeBase {
 // ============================================================================
 
 /// Classe Pessoa que herda de EntidadeBase
-/// Representa uma pessoa com nome, email e telefone
+/// Representa uma pessoa com nome, email, telefone e, opcionalmente, CPF ou CNPJ
 #[php_class]
 #[php(name = "Wead\\Pessoa")]
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Pessoa {
     /// ID da pessoa (herdado conceitualmente)
     pub id: Option<i64>,
@@ -88,6 +123,17 @@ pub struct Pessoa {
 
     /// Telefone da pessoa
     pub telefone: String,
+
+    /// Momento da exclusão lógica (timestamp Unix); None = ativa
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletado_em: Option<i64>,
+
+    /// Versão do registro (0 = nunca gravada), incrementada a cada atualização
+    pub versao: i64,
+
+    /// CPF ou CNPJ sem pontuação (None = não informado)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documento: Option<String>,
 }
 
 #[php_impl]
@@ -102,6 +148,9 @@ impl Pessoa {
             nome,
             email,
             telefone,
+            deletado_em: None,
+            versao: 0,
+            documento: None,
         }
     }
 
@@ -123,14 +172,15 @@ impl Pessoa {
         self.email.clone()
     }
 
-    /// Define o email da pessoa
-    /// @param string $email
-    /// @throws Exception Se o email for inválido
+    /// Define o email da pessoa, já normalizado (domínio em minúsculas e,
+    /// se internacional, em punycode), como em `analisar_email()`
+    /// @param string $email Espaços nas pontas são ignorados
+    /// @throws ValidacaoException Se o email for inválido
     pub fn definir_email(&mut self, email: String) -> PhpResult {
-        if !email.contains('@') {
-            return Err(PhpException::default("Email inválido: deve conter @".into()));
-        }
-        self.email = email;
+        let endereco = EnderecoEmail::analisar(email.trim()).map_err(|motivo| {
+            ValidacaoException::erro("email", format!("Email inválido: {}", motivo))
+        })?;
+        self.email = endereco.normalizado();
         Ok(())
     }
 
@@ -142,8 +192,33 @@ impl Pessoa {
 
     /// Define o telefone da pessoa
     /// @param string $telefone
-    pub fn definir_telefone(&mut self, telefone: String) {
-        self.telefone = telefone;
+    /// @param string|null $formato e164, nacional ou digitos para exigir um
+    ///   telefone brasileiro válido e gravá-lo nesse formato; null grava como recebido
+    /// @throws ValidacaoException Se o formato for informado e o telefone for inválido
+    pub fn definir_telefone(&mut self, telefone: String, formato: Option<String>) -> PhpResult {
+        self.telefone = match formato {
+            Some(formato) => analisar_telefone_ou_erro(&telefone)?.formatar(FormatoTelefone::de_nome(&formato)?),
+            None => telefone,
+        };
+        Ok(())
+    }
+
+    /// Obtém o CPF ou CNPJ da pessoa, sem pontuação
+    /// @return string|null
+    pub fn obter_documento(&self) -> Option<String> {
+        self.documento.clone()
+    }
+
+    /// Define o CPF ou CNPJ da pessoa (numérico ou alfanumérico), com ou sem
+    /// pontuação; é guardado sem pontuação e com letras maiúsculas
+    /// @param string|null $documento null ou vazio remove o documento
+    /// @throws ValidacaoException Se o documento for inválido
+    pub fn definir_documento(&mut self, documento: Option<String>) -> PhpResult {
+        self.documento = match documento.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(documento) => Some(analisar_documento_ou_erro(documento)?.valor().to_string()),
+        };
+        Ok(())
     }
 
     /// Obtém o ID da pessoa
@@ -166,25 +241,63 @@ impl Pessoa {
         map.insert("nome".to_string(), self.nome.clone());
         map.insert("email".to_string(), self.email.clone());
         map.insert("telefone".to_string(), self.telefone.clone());
+        map.insert(
+            "deletado_em".to_string(),
+            self.deletado_em.map(|t| t.to_string()).unwrap_or_default(),
+        );
+        map.insert("versao".to_string(), self.versao.to_string());
+        map.insert("documento".to_string(), self.documento.clone().unwrap_or_default());
         map
     }
 
+    /// Obtém a versão da pessoa, usada por `Storage::atualizar` para
+    /// detectar alterações concorrentes
+    /// @return int
+    pub fn obter_versao(&self) -> i64 {
+        self.versao
+    }
+
+    /// Define a versão da pessoa (ex.: recebida de volta de um formulário)
+    /// @param int $versao
+    pub fn definir_versao(&mut self, versao: i64) {
+        self.versao = versao;
+    }
+
+    /// Obtém o momento da exclusão lógica
+    /// @return int|null Timestamp Unix, ou null se a pessoa estiver ativa
+    pub fn obter_deletado_em(&self) -> Option<i64> {
+        self.deletado_em
+    }
+
+    /// Verifica se a pessoa foi deletada logicamente (soft delete)
+    /// @return bool
+    pub fn esta_deletada(&self) -> bool {
+        self.deletado_em.is_some()
+    }
+
     /// Valida os dados da pessoa
+    /// @param RegrasValidacao|null $regras Regras a aplicar (padrão: `new RegrasValidacao()`)
     /// @return bool
-    /// @throws Exception Se algum campo estiver inválido
-    pub fn validar(&self) -> PhpResult<bool> {
-        if self.nome.trim().is_empty() {
-            return Err(PhpException::default("Nome não pode ser vazio".into()));
-        }
-        if self.email.trim().is_empty() || !self.email.contains('@') {
-            return Err(PhpException::default("Email inválido".into()));
-        }
-        if self.telefone.trim().is_empty() {
-            return Err(PhpException::default("Telefone não pode ser vazio".into()));
-        }
+    /// @throws ValidacaoException Se algum campo estiver inválido (`obterErros()` traz todos)
+    pub fn validar(&self, regras: Option<&RegrasValidacao>) -> PhpResult<bool> {
+        regras.cloned().unwrap_or_default().verificar(self)?;
         Ok(true)
     }
 
+    /// Lista todas as regras de validação violadas, sem lançar exceção
+    /// @param RegrasValidacao|null $regras Regras a aplicar (padrão: `new RegrasValidacao()`)
+    /// @return array Lista de ['campo' => string, 'regra' => string, 'mensagem' => string];
+    ///   vazia se a pessoa for válida
+    pub fn erros_validacao(&self, regras: Option<&RegrasValidacao>) -> PhpResult<Vec<ZBox<ZendHashTable>>> {
+        regras
+            .cloned()
+            .unwrap_or_default()
+            .violacoes(self)
+            .iter()
+            .map(validacao::Violacao::para_array)
+            .collect()
+    }
+
     /// Representação em string da pessoa
     /// @return string
     pub fn __to_string(&self) -> String {
@@ -200,206 +313,375 @@ impl Pessoa {
 
 // ============================================================================
 // CLASSE: Storage
-// Gerencia persistência de dados em arquivo CSV
+// Gerencia persistência de dados através de um StorageBackend
 // Implementa o padrão Repository
 // ============================================================================
 
-/// Classe Storage para operações CRUD em arquivo CSV
-/// Gerencia o armazenamento e recuperação de pessoas
+/// Classe Storage para operações CRUD
+/// Gerencia o armazenamento e recuperação de pessoas; o formato físico
+/// fica a cargo do backend escolhido pelo DSN do construtor
+/// Pode ser percorrida com `foreach` sem carregar todos os registros
 #[php_class]
 #[php(name = "Wead\\Storage")]
-#[derive(Debug, Clone)]
+#[php(implements(ce = ce::aggregate, stub = "\\IteratorAggregate"))]
+#[derive(Debug)]
 pub struct Storage {
-    /// Caminho do arquivo CSV
-    caminho_arquivo: String,
+    /// Backend que efetivamente persiste os dados
+    backend: Box<dyn StorageBackend>,
+
+    /// Tempo máximo de espera por um bloqueio do arquivo
+    tempo_limite_bloqueio: Duration,
+
+    /// Razão de lixo que dispara a compactação automática (0 = desativada)
+    limite_compactacao: f64,
 
-    /// Último ID gerado (para auto-incremento)
-    ultimo_id: i64,
+    /// Se o email deve ser único entre as pessoas
+    email_unico: bool,
+
+    /// Regras aplicadas às pessoas gravadas
+    regras: RegrasValidacao,
+
+    /// Se registros malformados geram erro em vez de serem ignorados
+    modo_estrito: bool,
+
+    /// Quem está fazendo as alterações, gravado no histórico
+    ator: Option<String>,
+
+    /// Operações pendentes da transação em andamento (None = fora de transação)
+    transacao: Option<Vec<Operacao>>,
 }
 
+/// Tempo limite padrão para obter o bloqueio do arquivo (em milissegundos)
+const TEMPO_LIMITE_BLOQUEIO_PADRAO_MS: u64 = 5000;
+
 #[php_impl]
 impl Storage {
     /// Construtor do Storage
-    /// @param string $caminho_arquivo Caminho completo do arquivo CSV
-    /// @throws Exception Se o caminho for vazio
-    pub fn __construct(caminho_arquivo: String) -> PhpResult<Self> {
-        if caminho_arquivo.trim().is_empty() {
-            return Err(PhpException::default(
-                "Caminho do arquivo não pode ser vazio".into()
-            ));
-        }
+    /// @param string $dsn Caminho do arquivo CSV ou DSN do backend (ex.: csv:///tmp/pessoas.csv)
+    /// @throws ValidacaoException Se o DSN for vazio ou o backend não for suportado
+    /// @throws ArmazenamentoException Se o armazenamento não puder ser aberto
+    pub fn __construct(dsn: String) -> PhpResult<Self> {
+        let tempo_limite_bloqueio = Duration::from_millis(TEMPO_LIMITE_BLOQUEIO_PADRAO_MS);
+
+        Ok(Self {
+            backend: backend::abrir(&dsn, tempo_limite_bloqueio)?,
+            tempo_limite_bloqueio,
+            limite_compactacao: LIMITE_COMPACTACAO_PADRAO,
+            email_unico: false,
+            regras: RegrasValidacao::default(),
+            modo_estrito: false,
+            ator: None,
+            transacao: None,
+        })
+    }
 
-        let mut storage = Self {
-            caminho_arquivo,
-            ultimo_id: 0,
-        };
+    /// Cria (insere) uma nova pessoa
+    /// @param Pessoa $pessoa Pessoa a ser inserida
+    /// @return int ID da pessoa criada
+    /// @throws ValidacaoException Se algum campo estiver inválido
+    /// @throws ArmazenamentoException Se houver erro na escrita
+    pub fn criar(&mut self, pessoa: &mut Pessoa) -> PhpResult<i64> {
+        // Valida antes de inserir
+        self.regras.verificar(pessoa)?;
+        pessoa.versao = 1;
 
-        // Inicializa o arquivo se não existir
-        storage.inicializar_arquivo()?;
-        // Carrega o último ID
-        storage.carregar_ultimo_id()?;
+        if self.em_transacao() {
+            self.verificar_emails_pendentes(std::slice::from_ref(pessoa))?;
 
-        Ok(storage)
+            // O ID é reservado já; se a transação for desfeita ele é descartado
+            let novo_id = self.backend.reservar_ids(1)?;
+            pessoa.definir_id(novo_id);
+            self.registrar(Operacao::Criar(pessoa.clone()));
+            return Ok(novo_id);
+        }
+
+        self.backend.criar(pessoa)
     }
 
+    /// Cria várias pessoas de uma vez, com IDs consecutivos
+    /// Todas são validadas antes de qualquer escrita: se alguma for inválida,
+    /// nada é gravado. Os objetos recebidos não são alterados
+    /// @param array $pessoas Lista de Pessoa
+    /// @return array ['ids' => int[] na ordem recebida, 'erros' => [posição => mensagem]]
+    /// @throws ArmazenamentoException Se houver erro na escrita
+    pub fn criar_varios(&mut self, pessoas: &ZendHashTable) -> PhpResult<ZBox<ZendHashTable>> {
+        let mut validas = Vec::with_capacity(pessoas.len());
+        let mut erros = ZendHashTable::new();
+        let mut total_erros = 0;
+
+        // Emails já usados, para relatar duplicados por linha (o backend
+        // ainda confere tudo de novo sob o bloqueio de escrita)
+        let mut emails: HashMap<String, Option<i64>> = HashMap::new();
+        if self.email_unico {
+            for pessoa in self.fluxo()? {
+                let pessoa = pessoa?;
+                emails.insert(normalizar_email(&pessoa.email), pessoa.id);
+            }
+        }
 
+        for (posicao, valor) in pessoas.values().enumerate() {
+            let violacao = match valor.extract::<&Pessoa>() {
+                Some(pessoa) => match self.regras.violacoes(pessoa).first() {
+                    Some(violacao) => violacao.mensagem.clone(),
+                    None if self.email_unico => {
+                        match emails.insert(normalizar_email(&pessoa.email), None) {
+                            Some(dono @ Some(_)) => mensagem_email_duplicado(&pessoa.email, dono),
+                            Some(None) => format!("Email repetido no lote: {}", pessoa.email.trim()),
+                            None => {
+                                validas.push(pessoa.clone());
+                                continue;
+                            }
+                        }
+                    }
+                    None => {
+                        validas.push(pessoa.clone());
+                        continue;
+                    }
+                },
+                None => "Item não é uma Wead\\Pessoa".to_string(),
+            };
 
-    /// Cria (insere) uma nova pessoa no CSV
-    /// @param Pessoa $pessoa Pessoa a ser inserida
-    /// @return int ID da pessoa criada
-    /// @throws Exception Se houver erro na validação ou escrita
-    pub fn criar(&mut self, pessoa: &mut Pessoa) -> PhpResult<i64> {
-        // Valida antes de inserir
-        pessoa.validar()?;
-
-        // Atribui novo ID
-        let novo_id = self.proximo_id();
-        pessoa.definir_id(novo_id);
-
-        // Abre arquivo em modo append
-        let mut file = OpenOptions::new()
-            .append(true)
-            .open(&self.caminho_arquivo)
-            .map_err(|e| PhpException::default(
-                format!("Erro ao abrir arquivo: {}", e)
-            ))?;
-
-        // Escreve linha CSV
-        let linha = format!(
-            "{},{},{},{}\n",
-            pessoa.id.unwrap(),
-            Self::escapar_csv(&pessoa.nome),
-            Self::escapar_csv(&pessoa.email),
-            Self::escapar_csv(&pessoa.telefone)
-        );
+            erros.insert_at_index(posicao as i64, violacao)?;
+            total_erros += 1;
+        }
 
-        file.write_all(linha.as_bytes())
-            .map_err(|e| PhpException::default(
-                format!("Erro ao escrever no arquivo: {}", e)
-            ))?;
+        for pessoa in &mut validas {
+            pessoa.versao = 1;
+        }
 
-        Ok(novo_id)
+        let ids = if total_erros > 0 || validas.is_empty() {
+            Vec::new()
+        } else if self.em_transacao() {
+            let primeiro = self.backend.reservar_ids(validas.len() as i64)?;
+
+            validas
+                .into_iter()
+                .zip(primeiro..)
+                .map(|(mut pessoa, id)| {
+                    pessoa.definir_id(id);
+                    self.registrar(Operacao::Criar(pessoa));
+                    id
+                })
+                .collect()
+        } else {
+            self.backend.criar_varios(&mut validas)?
+        };
+
+        let mut resultado = ZendHashTable::new();
+        resultado.insert("ids", ids)?;
+        resultado.insert("erros", erros)?;
+        Ok(resultado)
     }
 
-    /// Busca uma pessoa por ID
+    /// Busca uma pessoa por ID (pessoas deletadas não são retornadas)
     /// @param int $id ID da pessoa
     /// @return Pessoa|null Pessoa encontrada ou null
-    pub fn buscar_por_id(&self, id: i64) -> Option<Pessoa> {
-        match self.listar_todas() {
-            Ok(pessoas) => {
-                pessoas.into_iter().find(|p| p.id == Some(id))
-            }
-            Err(_) => None
-        }
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn buscar_por_id(&self, id: i64) -> PhpResult<Option<Pessoa>> {
+        Ok(self.buscar_qualquer(id)?.filter(|p| p.deletado_em.is_none()))
     }
 
-    /// Lista todas as pessoas cadastradas
+    /// Lista todas as pessoas cadastradas (exceto as deletadas)
     /// @return array Array de Pessoa
-    /// @throws Exception Se houver erro na leitura
+    /// @throws ArmazenamentoException Se houver erro na leitura
     pub fn listar_todas(&self) -> PhpResult<Vec<Pessoa>> {
-        let file = File::open(&self.caminho_arquivo)
-            .map_err(|e| PhpException::default(
-                format!("Erro ao abrir arquivo: {}", e)
-            ))?;
+        let mut pessoas = self.pessoas_com_pendentes()?;
+        pessoas.retain(|p| p.deletado_em.is_none());
+        Ok(pessoas)
+    }
+
+    /// Lista todas as pessoas, inclusive as deletadas logicamente
+    /// @return array Array de Pessoa
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn listar_incluindo_deletados(&self) -> PhpResult<Vec<Pessoa>> {
+        self.pessoas_com_pendentes()
+    }
+
+    /// Iterador que lê as pessoas sob demanda (IteratorAggregate)
+    /// Ao contrário de `listar_todas`, não monta um array com todos os
+    /// registros: cada pessoa é lida do armazenamento quando consumida
+    /// As pessoas vêm na mesma ordem de `listar_todas`
+    /// @return IteradorPessoas
+    /// @throws ArmazenamentoException Se houver erro ao abrir o armazenamento
+    pub fn get_iterator(&self) -> PhpResult<IteradorPessoas> {
+        Ok(IteradorPessoas::new(self.fluxo_ativas()?))
+    }
+
+    /// Lista uma página de pessoas com ordenação
+    /// Só a página (e o que vem antes dela) é mantida em memória; o total
+    /// é contado durante a mesma leitura
+    /// @param int $offset Quantidade de pessoas a pular
+    /// @param int $limite Tamanho máximo da página
+    /// @param string $ordenarPor Campo: id, nome, email ou telefone (padrão id)
+    /// @param string $direcao asc ou desc (padrão asc)
+    /// @return array ['itens' => Pessoa[], 'total' => int, 'offset' => int, 'limite' => int]
+    /// @throws ValidacaoException Se algum parâmetro for inválido
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn listar_pagina(
+        &self,
+        offset: i64,
+        limite: i64,
+        ordenar_por: Option<String>,
+        direcao: Option<String>,
+    ) -> PhpResult<ZBox<ZendHashTable>> {
+        if offset < 0 || limite < 0 {
+            return Err(ValidacaoException::erro(
+                if offset < 0 { "offset" } else { "limite" },
+                "Offset e limite não podem ser negativos".into()
+            ));
+        }
 
-        let reader = BufReader::new(file);
-        let mut pessoas = Vec::new();
+        let campo = CampoOrdenacao::de_nome(ordenar_por.as_deref().unwrap_or("id"))?;
+        let descendente = match direcao.as_deref().unwrap_or("asc").to_lowercase().as_str() {
+            "asc" => false,
+            "desc" => true,
+            outra => return Err(ValidacaoException::erro(
+                "direcao",
+                format!("Direção de ordenação inválida: {} (use asc ou desc)", outra)
+            )),
+        };
 
-        for (i, line) in reader.lines().enumerate() {
-            if i == 0 { continue; } // Pula cabeçalho
+        let comparar = |a: &(ChaveOrdenacao, Pessoa), b: &(ChaveOrdenacao, Pessoa)| {
+            let ordem = a.0.cmp(&b.0);
+            let ordem = if descendente { ordem.reverse() } else { ordem };
+            // Desempate estável pelo ID, para as páginas não se sobreporem
+            ordem.then_with(|| a.1.id.cmp(&b.1.id))
+        };
 
-            let line = line.map_err(|e| PhpException::default(
-                format!("Erro ao ler linha: {}", e)
-            ))?;
+        // Mantém só as `offset + limite` primeiras; o buffer cresce até o
+        // dobro disso antes de ser reordenado e cortado
+        let necessarios = offset.saturating_add(limite) as usize;
+        let mut candidatas: Vec<(ChaveOrdenacao, Pessoa)> = Vec::new();
+        let mut total: i64 = 0;
 
-            if line.trim().is_empty() {
+        for pessoa in self.fluxo_ativas()? {
+            let pessoa = pessoa?;
+            total += 1;
+
+            if necessarios == 0 {
                 continue;
             }
+            candidatas.push((campo.chave(&pessoa), pessoa));
 
-            // Parse da linha CSV
-            let campos: Vec<&str> = line.split(',').collect();
-            if campos.len() >= 4 {
-                let id = campos[0].parse::<i64>().ok();
-                let nome = Self::desescapar_csv(campos[1]);
-                let email = Self::desescapar_csv(campos[2]);
-                let telefone = Self::desescapar_csv(campos[3]);
-
-                let pessoa = Pessoa {
-                    id,
-                    nome,
-                    email,
-                    telefone,
-                };
-
-                pessoas.push(pessoa);
+            if candidatas.len() >= necessarios.saturating_mul(2).max(1024) {
+                candidatas.sort_by(comparar);
+                candidatas.truncate(necessarios);
             }
         }
 
-        Ok(pessoas)
+        candidatas.sort_by(comparar);
+        let itens: Vec<Pessoa> = candidatas
+            .into_iter()
+            .skip(offset as usize)
+            .take(limite as usize)
+            .map(|(_, pessoa)| pessoa)
+            .collect();
+
+        let mut resultado = ZendHashTable::new();
+        resultado.insert("itens", itens)?;
+        resultado.insert("total", total)?;
+        resultado.insert("offset", offset)?;
+        resultado.insert("limite", limite)?;
+        Ok(resultado)
     }
 
-    /// Atualiza uma pessoa existente
+    /// Atualiza uma pessoa existente (pessoas deletadas não podem ser
+    /// atualizadas; restaure-as antes)
+    /// A versão da pessoa deve ser a armazenada; em caso de sucesso ela
+    /// recebe a nova versão, podendo ser atualizada de novo
     /// @param Pessoa $pessoa Pessoa com dados atualizados (deve ter ID)
     /// @return bool true se atualizado com sucesso
-    /// @throws Exception Se pessoa não tiver ID ou houver erro
-    pub fn atualizar(&self, pessoa: &Pessoa) -> PhpResult<bool> {
-        if pessoa.id.is_none() {
-            return Err(PhpException::default(
+    /// @throws ConflitoException Se a pessoa foi alterada desde que foi carregada
+    /// @throws ValidacaoException Se a pessoa não tiver ID ou algum campo for inválido
+    /// @throws NaoEncontradoException Se a pessoa não existir ou estiver deletada
+    /// @throws ArmazenamentoException Se houver erro na leitura ou escrita
+    pub fn atualizar(&mut self, pessoa: &mut Pessoa) -> PhpResult<bool> {
+        let Some(id_busca) = pessoa.id else {
+            return Err(ValidacaoException::erro(
+                "id",
                 "Pessoa deve ter ID para ser atualizada".into()
             ));
-        }
+        };
 
-        pessoa.validar()?;
+        self.regras.verificar(pessoa)?;
 
-        let mut pessoas = self.listar_todas()?;
-        let id_busca = pessoa.id.unwrap();
-        
-        // Busca e atualiza a pessoa
-        let mut encontrada = false;
-        for p in pessoas.iter_mut() {
-            if p.id == Some(id_busca) {
-                p.nome = pessoa.nome.clone();
-                p.email = pessoa.email.clone();
-                p.telefone = pessoa.telefone.clone();
-                encontrada = true;
-                break;
-            }
-        }
+        let anterior = match self.buscar_qualquer(id_busca)? {
+            Some(anterior) if anterior.deletado_em.is_none() => anterior,
+            _ => return Err(nao_encontrada(id_busca)),
+        };
 
-        if !encontrada {
-            return Err(PhpException::default(
-                format!("Pessoa com ID {} não encontrada", id_busca)
-            ));
+        if pessoa.versao != anterior.versao {
+            return Err(conflito_versao(id_busca, pessoa.versao, anterior.versao));
         }
 
-        // Reescreve o arquivo completo
-        self.reescrever_arquivo(&pessoas)?;
+        // A exclusão lógica só muda por deletar/restaurar
+        let mut nova = pessoa.clone();
+        nova.deletado_em = None;
+        pessoa.versao = self.gravar_atualizacao(anterior, nova)?;
+
         Ok(true)
     }
 
-    /// Deleta uma pessoa por ID
+    /// Deleta uma pessoa por ID (exclusão lógica: o registro é mantido com
+    /// `deletado_em` preenchido e pode ser restaurado)
     /// @param int $id ID da pessoa a ser deletada
     /// @return bool true se deletado com sucesso
-    /// @throws Exception Se pessoa não for encontrada
-    pub fn deletar(&self, id: i64) -> PhpResult<bool> {
-        let mut pessoas = self.listar_todas()?;
-        let tamanho_original = pessoas.len();
+    /// @throws NaoEncontradoException Se pessoa não for encontrada ou já estiver deletada
+    /// @throws ArmazenamentoException Se houver erro na leitura ou na escrita
+    pub fn deletar(&mut self, id: i64) -> PhpResult<bool> {
+        let anterior = match self.buscar_qualquer(id)? {
+            Some(anterior) if anterior.deletado_em.is_none() => anterior,
+            _ => return Err(nao_encontrada(id)),
+        };
 
-        // Remove a pessoa da lista
-        pessoas.retain(|p| p.id != Some(id));
+        let mut nova = anterior.clone();
+        nova.deletado_em = Some(agora());
+        self.gravar_atualizacao(anterior, nova)?;
 
-        if pessoas.len() == tamanho_original {
-            return Err(PhpException::default(
-                format!("Pessoa com ID {} não encontrada", id)
-            ));
+        Ok(true)
+    }
+
+    /// Restaura uma pessoa deletada logicamente
+    /// @param int $id ID da pessoa
+    /// @return bool true se restaurada, false se ela não estava deletada
+    /// @throws NaoEncontradoException Se pessoa não for encontrada
+    pub fn restaurar(&mut self, id: i64) -> PhpResult<bool> {
+        let Some(anterior) = self.buscar_qualquer(id)? else {
+            return Err(nao_encontrada(id));
+        };
+
+        if anterior.deletado_em.is_none() {
+            return Ok(false);
         }
 
-        // Reescreve o arquivo sem a pessoa deletada
-        self.reescrever_arquivo(&pessoas)?;
+        let mut nova = anterior.clone();
+        nova.deletado_em = None;
+        self.gravar_atualizacao(anterior, nova)?;
+
         Ok(true)
     }
 
-    /// Busca pessoas por nome (busca parcial, case-insensitive)
+    /// Remove definitivamente uma pessoa (deletada ou não); não pode ser desfeito
+    /// @param int $id ID da pessoa
+    /// @return bool true se removida com sucesso
+    /// @throws NaoEncontradoException Se pessoa não for encontrada
+    pub fn expurgar(&mut self, id: i64) -> PhpResult<bool> {
+        if self.buscar_qualquer(id)?.is_none() {
+            return Err(nao_encontrada(id));
+        }
+
+        if self.registrar(Operacao::Deletar(id)) {
+            return Ok(true);
+        }
+
+        if !self.backend.deletar(id)? {
+            return Err(nao_encontrada(id));
+        }
+
+        Ok(true)
+    }
+
+    /// Busca pessoas por nome (busca parcial, case-insensitive, sem as deletadas)
     /// @param string $nome Nome ou parte do nome
     /// @return array Array de Pessoa
     pub fn buscar_por_nome(&self, nome: String) -> PhpResult<Vec<Pessoa>> {
@@ -414,146 +696,672 @@ impl Storage {
         Ok(resultado)
     }
 
-    /// Conta o total de pessoas cadastradas
+    /// Busca pessoas pelo CPF ou CNPJ (sem as deletadas)
+    /// @param string $documento CPF ou CNPJ, com ou sem pontuação
+    /// @return array Array de Pessoa
+    /// @throws ValidacaoException Se o documento for inválido
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn buscar_por_documento(&self, documento: String) -> PhpResult<Vec<Pessoa>> {
+        let documento = analisar_documento_ou_erro(&documento)?;
+
+        let mut encontradas = Vec::new();
+        for pessoa in self.fluxo_ativas()? {
+            let pessoa = pessoa?;
+            if pessoa.documento.as_deref() == Some(documento.valor()) {
+                encontradas.push(pessoa);
+            }
+        }
+        Ok(encontradas)
+    }
+
+    /// Busca as pessoas que satisfazem a consulta
+    /// O filtro é avaliado em Rust enquanto os registros são lidos, e a
+    /// leitura para assim que o limite da consulta é atingido
+    /// @param Consulta $consulta
+    /// @return array Array de Pessoa
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn consultar(&self, consulta: &Consulta) -> PhpResult<Vec<Pessoa>> {
+        consulta.aplicar(self.fluxo_ativas()?)
+    }
+
+    /// Conta o total de pessoas cadastradas (exceto as deletadas)
     /// @return int Total de pessoas
     pub fn contar(&self) -> PhpResult<i64> {
-        let pessoas = self.listar_todas()?;
-        Ok(pessoas.len() as i64)
+        if self.em_transacao() {
+            return Ok(self.listar_todas()?.len() as i64);
+        }
+        self.backend.contar()
     }
 
 
-    /// Limpa todos os registros do arquivo (mantém cabeçalho)
+    /// Limpa todos os registros (mantém cabeçalho e histórico de alterações)
+    /// Cada registro removido entra no histórico como expurgado
     /// @return bool true se sucesso
-    pub fn limpar_todos(&self) -> PhpResult<bool> {
-        self.reescrever_arquivo(&[])?;
+    pub fn limpar_todos(&mut self) -> PhpResult<bool> {
+        if !self.registrar(Operacao::Limpar) {
+            self.backend.limpar()?;
+        }
         Ok(true)
     }
 
-    /// Obtém o caminho do arquivo
+    /// Obtém o caminho do arquivo (ou identificação do backend)
     /// @return string
     pub fn obter_caminho(&self) -> String {
-        self.caminho_arquivo.clone()
+        self.backend.caminho()
     }
-}
 
-impl Storage {
-    /// Inicializa o arquivo CSV com cabeçalhos se não existir
-    fn inicializar_arquivo(&self) -> PhpResult {
-        let path = Path::new(&self.caminho_arquivo);
-        
-        if !path.exists() {
-            // Cria diretório se não existir
-            if let Some(parent) = path.parent() {
-                if !parent.exists() {
-                    std::fs::create_dir_all(parent)
-                        .map_err(|e| PhpException::default(
-                            format!("Erro ao criar diretório: {}", e)
-                        ))?;
-                }
+    /// Define o tempo máximo de espera pelo bloqueio do arquivo
+    /// @param int $milissegundos Tempo limite (0 = tenta uma única vez)
+    /// @throws ValidacaoException Se o valor for negativo
+    pub fn definir_tempo_limite_bloqueio(&mut self, milissegundos: i64) -> PhpResult {
+        if milissegundos < 0 {
+            return Err(ValidacaoException::erro(
+                "tempo_limite_bloqueio",
+                "Tempo limite do bloqueio não pode ser negativo".into()
+            ));
+        }
+        self.tempo_limite_bloqueio = Duration::from_millis(milissegundos as u64);
+        self.backend.definir_tempo_limite_bloqueio(self.tempo_limite_bloqueio);
+        Ok(())
+    }
+
+    /// Obtém o tempo máximo de espera pelo bloqueio do arquivo
+    /// @return int Tempo limite em milissegundos
+    pub fn obter_tempo_limite_bloqueio(&self) -> i64 {
+        self.tempo_limite_bloqueio.as_millis() as i64
+    }
+
+    /// Liga ou desliga a restrição de email único (comparação sem diferenciar
+    /// maiúsculas). Com ela ativa, `criar`, `criar_varios`, `atualizar` e
+    /// `upsert` lançam DuplicidadeException se o email já for de outra pessoa
+    /// Registros duplicados que já existiam não são alterados; pessoas
+    /// deletadas continuam reservando o email até serem expurgadas
+    /// @param bool $ativo
+    pub fn definir_email_unico(&mut self, ativo: bool) {
+        self.email_unico = ativo;
+        self.backend.definir_email_unico(ativo);
+    }
+
+    /// Indica se a restrição de email único está ativa
+    /// @return bool
+    pub fn obter_email_unico(&self) -> bool {
+        self.email_unico
+    }
+
+    /// Liga ou desliga o modo estrito de leitura. Por padrão registros
+    /// malformados do arquivo são ignorados (ou aproveitados, se der);
+    /// no modo estrito a primeira linha inválida gera uma exceção com o
+    /// caminho do arquivo, o número da linha e o conteúdo dela
+    /// Vale também para o arquivo lido por `importar_csv`
+    /// @param bool $ativo
+    pub fn definir_modo_estrito(&mut self, ativo: bool) {
+        self.modo_estrito = ativo;
+        self.backend.definir_modo_estrito(ativo);
+    }
+
+    /// Indica se o modo estrito de leitura está ativo
+    /// @return bool
+    pub fn obter_modo_estrito(&self) -> bool {
+        self.modo_estrito
+    }
+
+    /// Define as regras de validação aplicadas por `criar`, `criar_varios`,
+    /// `atualizar` e `upsert` (registros já gravados não são revalidados)
+    /// @param RegrasValidacao $regras
+    pub fn definir_regras_validacao(&mut self, regras: &RegrasValidacao) {
+        self.regras = regras.clone();
+    }
+
+    /// Obtém uma cópia das regras de validação em uso
+    /// @return RegrasValidacao
+    pub fn obter_regras_validacao(&self) -> RegrasValidacao {
+        self.regras.clone()
+    }
+
+    /// Insere ou atualiza uma pessoa pela chave natural informada
+    /// Se existir uma pessoa com o mesmo valor na chave, ela é atualizada
+    /// (e a pessoa recebida assume o ID dela, sendo restaurada se estiver
+    /// deletada); senão a pessoa é criada
+    /// @param Pessoa $pessoa Pessoa a ser gravada
+    /// @param string $chave Campo usado como chave: email (padrão), telefone ou documento
+    /// @return int ID da pessoa criada ou atualizada
+    /// @throws DuplicidadeException Se mais de uma pessoa tiver o mesmo valor na chave
+    /// @throws ValidacaoException Se a chave ou algum campo for inválido
+    /// @throws ArmazenamentoException Se houver erro na leitura ou escrita
+    pub fn upsert(&mut self, pessoa: &mut Pessoa, chave: Option<String>) -> PhpResult<i64> {
+        self.regras.verificar(pessoa)?;
+
+        let chave = chave.unwrap_or_else(|| "email".into()).to_lowercase();
+        if chave != "email" && chave != "telefone" && chave != "documento" {
+            return Err(ValidacaoException::erro(
+                "chave",
+                format!("Chave de upsert inválida: {} (use email, telefone ou documento)", chave)
+            ));
+        }
+
+        let valor_da = |p: &Pessoa| -> String {
+            match chave.as_str() {
+                "email" => normalizar_email(&p.email),
+                "telefone" => p.telefone.trim().to_string(),
+                _ => p.documento.clone().unwrap_or_default(),
             }
+        };
 
-            // Cria arquivo com cabeçalho
-            let mut file = File::create(path)
-                .map_err(|e| PhpException::default(
-                    format!("Erro ao criar arquivo: {}", e)
-                ))?;
+        let valor = valor_da(pessoa);
+        if chave == "documento" && valor.is_empty() {
+            return Err(ValidacaoException::erro(
+                "documento",
+                "Upsert por documento exige que a pessoa tenha documento".into()
+            ));
+        }
+        let mut encontradas = Vec::new();
+        for existente in self.fluxo()? {
+            let existente = existente?;
+            if valor_da(&existente) == valor {
+                encontradas.push(existente);
+            }
+        }
 
-            writeln!(file, "id,nome,email,telefone")
-                .map_err(|e| PhpException::default(
-                    format!("Erro ao escrever cabeçalho: {}", e)
-                ))?;
+        let mut encontradas = encontradas.into_iter();
+        match (encontradas.next(), encontradas.next()) {
+            (None, _) => self.criar(pessoa),
+            (Some(anterior @ Pessoa { id: Some(id), .. }), None) => {
+                // Uma pessoa deletada com a mesma chave é restaurada
+                pessoa.definir_id(id);
+                let mut nova = pessoa.clone();
+                nova.deletado_em = None;
+                pessoa.versao = self.gravar_atualizacao(anterior, nova)?;
+                Ok(id)
+            }
+            _ => Err(DuplicidadeException::erro(
+                format!("Mais de uma pessoa com {} igual a {}; upsert ambíguo", chave, valor)
+            )),
         }
+    }
+
+    /// Compacta o armazenamento, descartando versões antigas e remoções
+    /// acumuladas por `atualizar`/`deletar`
+    /// @return int Quantidade de entradas obsoletas descartadas
+    /// @throws ArmazenamentoException Se houver erro na reescrita
+    pub fn compactar(&mut self) -> PhpResult<i64> {
+        self.exigir_fora_de_transacao("compactar")?;
+        self.backend.compactar()
+    }
+
+    /// Obtém a versão do esquema em que os dados estão gravados
+    /// Arquivos CSV antigos podem estar em uma versão anterior à atual
+    /// até serem migrados (o que acontece na primeira escrita)
+    /// @return int
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn obter_versao_esquema(&self) -> PhpResult<i64> {
+        self.backend.versao_esquema()
+    }
+
+    /// Converte os dados gravados para o esquema atual, no próprio arquivo
+    /// (reescrita atômica). Colunas fora do esquema são descartadas
+    /// @return bool true se houve migração, false se já estava atualizado
+    /// @throws ArmazenamentoException Se houver erro na reescrita
+    pub fn migrar_esquema(&mut self) -> PhpResult<bool> {
+        self.exigir_fora_de_transacao("migrar_esquema")?;
+        self.backend.migrar_esquema()
+    }
+
+    /// Confere a integridade dos dados gravados sem lançar exceção pelos
+    /// problemas encontrados (independe do modo estrito)
+    /// @return array ['valido' => bool, 'total' => int,
+    ///   'linhas_invalidas' => [['linha' => int, 'conteudo' => string, 'motivo' => string]],
+    ///   'ids_duplicados' => int[], 'emails_invalidos' => [['id' => int|null, 'email' => string]]]
+    /// @throws ArmazenamentoException Se o armazenamento não puder ser lido
+    pub fn verificar(&self) -> PhpResult<ZBox<ZendHashTable>> {
+        let verificacao = self.backend.verificar()?;
+
+        let mut linhas_invalidas = Vec::with_capacity(verificacao.linhas_invalidas.len());
+        for invalida in verificacao.linhas_invalidas {
+            let mut linha = ZendHashTable::new();
+            linha.insert("linha", invalida.linha as i64)?;
+            linha.insert("conteudo", invalida.conteudo)?;
+            linha.insert("motivo", invalida.motivo)?;
+            linhas_invalidas.push(linha);
+        }
+
+        let mut emails_invalidos = Vec::new();
+        for pessoa in &verificacao.pessoas {
+            if !validar_email(pessoa.email.clone()) {
+                let mut email = ZendHashTable::new();
+                email.insert("id", pessoa.id)?;
+                email.insert("email", pessoa.email.clone())?;
+                emails_invalidos.push(email);
+            }
+        }
+
+        let valido = linhas_invalidas.is_empty()
+            && verificacao.ids_duplicados.is_empty()
+            && emails_invalidos.is_empty();
+
+        let mut resultado = ZendHashTable::new();
+        resultado.insert("valido", valido)?;
+        resultado.insert("total", verificacao.pessoas.len() as i64)?;
+        resultado.insert("linhas_invalidas", linhas_invalidas)?;
+        resultado.insert("ids_duplicados", verificacao.ids_duplicados)?;
+        resultado.insert("emails_invalidos", emails_invalidos)?;
+        Ok(resultado)
+    }
+
+    /// Define a fração de entradas obsoletas que dispara a compactação
+    /// automática após `atualizar`/`deletar`
+    /// @param float $razao Valor entre 0 e 1 (0 desativa a compactação automática)
+    /// @throws ValidacaoException Se o valor estiver fora do intervalo
+    pub fn definir_limite_compactacao(&mut self, razao: f64) -> PhpResult {
+        if !(0.0..=1.0).contains(&razao) {
+            return Err(ValidacaoException::erro(
+                "limite_compactacao",
+                "Limite de compactação deve estar entre 0 e 1".into()
+            ));
+        }
+        self.limite_compactacao = razao;
+        self.backend.definir_limite_compactacao(razao);
         Ok(())
     }
 
-    /// Carrega o último ID do arquivo para gerar próximo ID
-    fn carregar_ultimo_id(&mut self) -> PhpResult {
-        let file = File::open(&self.caminho_arquivo)
-            .map_err(|e| PhpException::default(
-                format!("Erro ao abrir arquivo: {}", e)
-            ))?;
+    /// Obtém a fração de entradas obsoletas que dispara a compactação automática
+    /// @return float
+    pub fn obter_limite_compactacao(&self) -> f64 {
+        self.limite_compactacao
+    }
 
-        let reader = BufReader::new(file);
-        let mut max_id = 0i64;
+    /// Exporta todos os registros para um arquivo CSV (o conteúdo anterior
+    /// do arquivo é substituído; os IDs são preservados)
+    /// @param string $caminho Caminho do arquivo CSV de destino
+    /// @return int Quantidade de pessoas exportadas
+    /// @throws ArmazenamentoException Se houver erro na leitura ou escrita
+    pub fn exportar_csv(&self, caminho: String) -> PhpResult<i64> {
+        let pessoas = self.listar_incluindo_deletados()?;
+        let total = pessoas.len() as i64;
 
-        // Pula a primeira linha (cabeçalho) e busca o maior ID
-        for (i, line) in reader.lines().enumerate() {
-            if i == 0 { continue; } // Pula cabeçalho
+        ArquivoBackend::new(&caminho, Box::new(FormatoCsv), self.tempo_limite_bloqueio)?
+            .substituir_todos(pessoas)?;
 
-            let line = line.map_err(|e| PhpException::default(
-                format!("Erro ao ler linha: {}", e)
-            ))?;
+        Ok(total)
+    }
 
-            if let Some(id_str) = line.split(',').next() {
-                if let Ok(id) = id_str.parse::<i64>() {
-                    if id > max_id {
-                        max_id = id;
-                    }
-                }
+    /// Carrega os registros de um arquivo CSV, substituindo todo o conteúdo
+    /// atual deste Storage (os IDs do arquivo são preservados)
+    /// @param string $caminho Caminho do arquivo CSV de origem
+    /// @return int Quantidade de pessoas importadas
+    /// @throws ArmazenamentoException Se o arquivo não existir (nada é alterado)
+    ///   ou houver erro na leitura ou escrita
+    pub fn importar_csv(&mut self, caminho: String) -> PhpResult<i64> {
+        self.exigir_fora_de_transacao("importar_csv")?;
+
+        // ArquivoBackend::new criaria um arquivo vazio no lugar de um caminho
+        // errado, e importá-lo apagaria todo o conteúdo atual
+        let arquivo = Path::new(&caminho);
+        match std::fs::metadata(arquivo) {
+            Ok(metadados) if metadados.is_file() => {}
+            Ok(_) => {
+                let erro = io::Error::new(io::ErrorKind::InvalidInput, "não é um arquivo");
+                return Err(ArmazenamentoException::io(arquivo, "Arquivo de importação inválido", &erro));
+            }
+            Err(e) => {
+                return Err(ArmazenamentoException::io(arquivo, "Arquivo de importação inacessível", &e));
             }
         }
 
-        self.ultimo_id = max_id;
+        let mut origem = ArquivoBackend::new(&caminho, Box::new(FormatoCsv), self.tempo_limite_bloqueio)?;
+        origem.definir_modo_estrito(self.modo_estrito);
+        let pessoas = origem.listar()?;
+        let total = pessoas.len() as i64;
+
+        self.backend.substituir_todos(pessoas)?;
+        Ok(total)
+    }
+
+    /// Inicia uma transação: `criar`, `atualizar`, `deletar` e `limpar_todos`
+    /// ficam pendentes (visíveis apenas nesta instância) até
+    /// `confirmar_transacao()`, que os grava de uma só vez
+    /// Equivalente PHP:
+    /// ```php
+    /// $storage->iniciarTransacao();
+    /// try {
+    ///     foreach ($linhas as $linha) { $storage->criar($linha); }
+    ///     $storage->confirmarTransacao();
+    /// } catch (\Throwable $e) {
+    ///     if ($storage->emTransacao()) { $storage->desfazerTransacao(); }
+    ///     throw $e;
+    /// }
+    /// ```
+    /// @throws Exception Se já houver uma transação em andamento (código CODIGO_TRANSACAO)
+    pub fn iniciar_transacao(&mut self) -> PhpResult {
+        if self.em_transacao() {
+            return Err(WeadException::erro(
+                WeadException::CODIGO_TRANSACAO,
+                "Já existe uma transação em andamento".into()
+            ));
+        }
+        self.transacao = Some(Vec::new());
         Ok(())
     }
 
-    /// Gera o próximo ID disponível
-    fn proximo_id(&mut self) -> i64 {
-        self.ultimo_id += 1;
-        self.ultimo_id
+    /// Grava atomicamente todas as operações pendentes (uma única reescrita),
+    /// junto com o histórico delas
+    /// Se a gravação falhar, nada é aplicado e a transação é encerrada
+    /// @throws Exception Se não houver transação (código CODIGO_TRANSACAO)
+    /// @throws ArmazenamentoException Se houver erro na escrita
+    pub fn confirmar_transacao(&mut self) -> PhpResult {
+        let Some(operacoes) = self.transacao.take() else {
+            return Err(WeadException::erro(
+                WeadException::CODIGO_TRANSACAO,
+                "Nenhuma transação em andamento".into()
+            ));
+        };
+
+        if operacoes.is_empty() {
+            return Ok(());
+        }
+
+        // O histórico é montado pelo backend sobre o estado que ele lê sob o
+        // bloqueio da gravação, e não sobre uma leitura feita antes dela
+        self.backend.aplicar(operacoes)
+    }
+
+    /// Descarta as operações pendentes; o armazenamento não é alterado
+    /// (IDs reservados por `criar` durante a transação não são reaproveitados)
+    /// @throws Exception Se não houver transação em andamento (código CODIGO_TRANSACAO)
+    pub fn desfazer_transacao(&mut self) -> PhpResult {
+        if self.transacao.take().is_none() {
+            return Err(WeadException::erro(
+                WeadException::CODIGO_TRANSACAO,
+                "Nenhuma transação em andamento".into()
+            ));
+        }
+        Ok(())
     }
 
-    /// Reescreve o arquivo CSV completamente com nova lista
-    fn reescrever_arquivo(&self, pessoas: &[Pessoa]) -> PhpResult {
-        let mut file = File::create(&self.caminho_arquivo)
-            .map_err(|e| PhpException::default(
-                format!("Erro ao criar arquivo: {}", e)
-            ))?;
+    /// Verifica se há uma transação em andamento
+    /// @return bool
+    pub fn em_transacao(&self) -> bool {
+        self.transacao.is_some()
+    }
 
-        // Escreve cabeçalho
-        writeln!(file, "id,nome,email,telefone")
-            .map_err(|e| PhpException::default(
-                format!("Erro ao escrever cabeçalho: {}", e)
-            ))?;
+    /// Define quem está fazendo as próximas alterações (usuário, processo...),
+    /// gravado no histórico junto com cada alteração
+    /// @param string|null $ator null para não identificar
+    pub fn definir_ator(&mut self, ator: Option<String>) {
+        self.backend.definir_ator(ator.clone());
+        self.ator = ator;
+    }
+
+    /// Obtém quem está fazendo as alterações
+    /// @return string|null
+    pub fn obter_ator(&self) -> Option<String> {
+        self.ator.clone()
+    }
 
-        // Escreve cada pessoa
-        for pessoa in pessoas {
-            let linha = format!(
-                "{},{},{},{}\n",
-                pessoa.id.unwrap_or(0),
-                Self::escapar_csv(&pessoa.nome),
-                Self::escapar_csv(&pessoa.email),
-                Self::escapar_csv(&pessoa.telefone)
-            );
+    /// Histórico de alterações de uma pessoa (criar, atualizar, deletar,
+    /// restaurar e expurgar), do mais antigo para o mais recente
+    /// Alterações de uma transação entram no histórico ao confirmá-la
+    /// O histórico é gravado junto com a alteração: se não puder ser gravado
+    /// (ex.: disco cheio), a alteração também falha
+    /// @param int $id ID da pessoa
+    /// @return array Array de Alteracao
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn historico(&self, id: i64) -> PhpResult<Vec<Alteracao>> {
+        self.backend.historico(id)
+    }
 
-        file.write_all(linha.as_bytes())
-                .map_err(|e| PhpException::default(
-                    format!("Erro ao escrever no arquivo: {}", e)
-                ))?;
+    /// Busca uma pessoa como ela estava no momento informado, segundo o
+    /// histórico (pessoas deletadas naquele momento vêm com `deletado_em`)
+    /// Sem nenhuma alteração registrada, vale a versão atual
+    /// @param int $id ID da pessoa
+    /// @param int $momento Timestamp Unix
+    /// @return Pessoa|null null se a pessoa não existia no momento
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn buscar_em(&self, id: i64, momento: i64) -> PhpResult<Option<Pessoa>> {
+        match estado_em(&self.backend.historico(id)?, momento) {
+            Some(versao) => Ok(versao),
+            None => self.buscar_qualquer(id),
         }
+    }
 
+    /// Zera todos os armazenamentos em memória nomeados (`memory:nome`),
+    /// inclusive a sequência de IDs. Útil entre um teste e outro
+    pub fn resetar_memoria() {
+        MemoriaBackend::resetar_todos();
+    }
+}
+
+impl Storage {
+    /// Registra uma operação na transação em andamento
+    /// @return false se não houver transação (a operação deve ser aplicada já)
+    fn registrar(&mut self, operacao: Operacao) -> bool {
+        match self.transacao.as_mut() {
+            Some(operacoes) => {
+                operacoes.push(operacao);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pessoas sob demanda, considerando a transação em andamento
+    fn fluxo(&self) -> PhpResult<FluxoPessoas> {
+        if self.em_transacao() {
+            return Ok(Box::new(self.pessoas_com_pendentes()?.into_iter().map(Ok)));
+        }
+        self.backend.iterar()
+    }
+
+    /// Pessoas ativas (não deletadas) sob demanda
+    fn fluxo_ativas(&self) -> PhpResult<FluxoPessoas> {
+        let fluxo = self.fluxo()?;
+        Ok(Box::new(fluxo.filter(|p| !p.as_ref().is_ok_and(|p| p.deletado_em.is_some()))))
+    }
+
+    /// Busca uma pessoa por ID, inclusive deletada, considerando a transação
+    fn buscar_qualquer(&self, id: i64) -> PhpResult<Option<Pessoa>> {
+        if self.em_transacao() {
+            return Ok(self.pessoas_com_pendentes()?.into_iter().find(|p| p.id == Some(id)));
+        }
+        self.backend.buscar(id)
+    }
+
+    /// Grava uma nova versão de uma pessoa existente, já validada,
+    /// registrando a alteração no histórico
+    /// @return a versão gravada (a seguinte à de `anterior`)
+    fn gravar_atualizacao(&mut self, anterior: Pessoa, mut nova: Pessoa) -> PhpResult<i64> {
+        nova.versao = anterior.versao + 1;
+        let versao = nova.versao;
+
+        if self.em_transacao() {
+            self.verificar_emails_pendentes(std::slice::from_ref(&nova))?;
+            self.registrar(Operacao::Atualizar(nova));
+            return Ok(versao);
+        }
+
+        let Some(id) = nova.id else {
+            return Err(ValidacaoException::erro(
+                "id",
+                "Pessoa deve ter ID para ser atualizada".into()
+            ));
+        };
+        if !self.backend.atualizar(&nova)? {
+            return Err(nao_encontrada(id));
+        }
+        Ok(versao)
+    }
+
+    /// Pessoas como vistas por esta instância: o conteúdo do backend mais
+    /// as operações pendentes da transação em andamento
+    fn pessoas_com_pendentes(&self) -> PhpResult<Vec<Pessoa>> {
+        let mut pessoas = self.backend.listar()?;
+
+        if let Some(operacoes) = self.transacao.as_ref() {
+            backend::aplicar_operacoes(&mut pessoas, operacoes)?;
+        }
+        Ok(pessoas)
+    }
+
+    /// Confere o email único contra o que esta instância vê durante a
+    /// transação; a conferência definitiva acontece ao confirmar
+    fn verificar_emails_pendentes(&self, novas: &[Pessoa]) -> PhpResult {
+        if !self.email_unico {
+            return Ok(());
+        }
+        verificar_emails_unicos(&self.pessoas_com_pendentes()?, novas)
+    }
+
+    /// Rejeita operações que não podem participar de uma transação
+    fn exigir_fora_de_transacao(&self, operacao: &str) -> PhpResult {
+        if self.em_transacao() {
+            return Err(WeadException::erro(
+                WeadException::CODIGO_TRANSACAO,
+                format!("{} não é permitido durante uma transação", operacao)
+            ));
+        }
         Ok(())
     }
+}
 
-    /// Escapa string para formato CSV (adiciona aspas se necessário)
-    fn escapar_csv(s: &str) -> String {
-        if s.contains(',') || s.contains('"') || s.contains('\n') {
-            format!("\"{}\"", s.replace('"', "\"\""))
-        } else {
-            s.to_string()
+/// Momento atual como timestamp Unix (segundos)
+fn agora() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Campo usado por `Storage::listar_pagina` para ordenar
+#[derive(Debug, Clone, Copy)]
+enum CampoOrdenacao {
+    Id,
+    Nome,
+    Email,
+    Telefone,
+}
+
+/// Valor de ordenação já calculado de uma pessoa
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum ChaveOrdenacao {
+    Numero(Option<i64>),
+    Texto(ChaveColacao),
+}
+
+impl CampoOrdenacao {
+    /// Interpreta o nome do campo recebido do PHP
+    fn de_nome(nome: &str) -> PhpResult<Self> {
+        match nome.to_lowercase().as_str() {
+            "id" => Ok(Self::Id),
+            "nome" => Ok(Self::Nome),
+            "email" => Ok(Self::Email),
+            "telefone" => Ok(Self::Telefone),
+            _ => Err(ValidacaoException::erro(
+                "ordenar_por",
+                format!("Campo de ordenação inválido: {} (use id, nome, email ou telefone)", nome)
+            )),
         }
     }
 
-    /// Remove escape de string CSV
-    fn desescapar_csv(s: &str) -> String {
-        let trimmed = s.trim();
-        if trimmed.starts_with('"') && trimmed.ends_with('"') {
-            trimmed[1..trimmed.len()-1].replace("\"\"", "\"")
-        } else {
-            trimmed.to_string()
+    /// Chave de ordenação da pessoa; textos usam a colação pt-BR
+    fn chave(self, pessoa: &Pessoa) -> ChaveOrdenacao {
+        match self {
+            Self::Id => ChaveOrdenacao::Numero(pessoa.id),
+            Self::Nome => ChaveOrdenacao::Texto(ChaveColacao::new(&pessoa.nome)),
+            Self::Email => ChaveOrdenacao::Texto(ChaveColacao::new(&pessoa.email)),
+            Self::Telefone => ChaveOrdenacao::Texto(ChaveColacao::new(&pessoa.telefone)),
         }
     }
+}
+
+// ============================================================================
+// CLASSE: IteradorPessoas
+// Iterador PHP sobre as pessoas de um Storage, lidas sob demanda
+// ============================================================================
 
+/// Iterador retornado por `Storage::getIterator()`
+/// Mantém em memória apenas a pessoa atual; como a leitura é sequencial,
+/// não pode ser reiniciado depois de avançar (use um novo `foreach`)
+#[php_class]
+#[php(name = "Wead\\IteradorPessoas")]
+#[php(implements(ce = ce::iterator, stub = "\\Iterator"))]
+pub struct IteradorPessoas {
+    /// Pessoas ainda não lidas
+    fluxo: FluxoPessoas,
+
+    /// Pessoa na posição atual (None = fim)
+    atual: Option<Pessoa>,
+
+    /// Posição atual, começando em 0
+    posicao: i64,
+
+    /// Se a primeira pessoa já foi lida
+    iniciado: bool,
+}
+
+impl IteradorPessoas {
+    /// Cria o iterador sem ler nada ainda
+    fn new(fluxo: FluxoPessoas) -> Self {
+        Self {
+            fluxo,
+            atual: None,
+            posicao: 0,
+            iniciado: false,
+        }
+    }
+
+    /// Lê a próxima pessoa do fluxo
+    fn avancar(&mut self) -> PhpResult {
+        self.iniciado = true;
+        self.atual = self.fluxo.next().transpose()?;
+        Ok(())
+    }
+}
+
+#[php_impl]
+impl IteradorPessoas {
+    /// Posiciona na primeira pessoa
+    /// @throws Exception Se o iterador já tiver avançado ou houver erro na leitura
+    pub fn rewind(&mut self) -> PhpResult {
+        if !self.iniciado {
+            return self.avancar();
+        }
+        if self.posicao > 0 {
+            return Err(WeadException::erro(
+                WeadException::CODIGO_GERAL,
+                "IteradorPessoas não pode ser reiniciado; obtenha um novo com getIterator()".into()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Verifica se há uma pessoa na posição atual
+    /// @return bool
+    pub fn valid(&mut self) -> PhpResult<bool> {
+        if !self.iniciado {
+            self.avancar()?;
+        }
+        Ok(self.atual.is_some())
+    }
+
+    /// Pessoa na posição atual
+    /// @return Pessoa|null
+    pub fn current(&self) -> Option<Pessoa> {
+        self.atual.clone()
+    }
+
+    /// Posição atual (0, 1, 2...), como nos índices de `listar_todas`
+    /// @return int
+    pub fn key(&self) -> i64 {
+        self.posicao
+    }
+
+    /// Avança para a próxima pessoa
+    /// @throws ArmazenamentoException Se houver erro na leitura
+    pub fn next(&mut self) -> PhpResult {
+        if !self.iniciado {
+            self.avancar()?;
+        }
+        self.posicao += 1;
+        self.avancar()
+    }
 }
 
 // ============================================================================
@@ -561,24 +1369,133 @@ impl Storage {
 // Funções utilitárias disponíveis globalmente no namespace Wead
 // ============================================================================
 
-/// Formata um telefone brasileiro (remove caracteres especiais)
+/// Formata um telefone brasileiro
 /// @param string $telefone Telefone com ou sem formatação
-/// @return string Telefone apenas com números
+/// @param string|null $formato e164 (+5511987654321), nacional ((11) 98765-4321)
+///   ou digitos (11987654321); null só remove os caracteres que não são dígitos,
+///   sem validar
+/// @return string Telefone formatado
+/// @throws ValidacaoException Se o formato for informado e o telefone for inválido
 #[php_function]
 #[php(name = "Wead\\formatar_telefone")]
-pub fn formatar_telefone(telefone: String) -> String {
-    telefone.chars()
-        .filter(|c| c.is_ascii_digit())
-        .collect()
+pub fn formatar_telefone(telefone: String, formato: Option<String>) -> PhpResult<String> {
+    match formato {
+        Some(formato) => Ok(analisar_telefone_ou_erro(&telefone)?.formatar(FormatoTelefone::de_nome(&formato)?)),
+        None => Ok(telefone.chars().filter(|c| c.is_ascii_digit()).collect()),
     }
+}
 
-/// Valida formato de email básico
+/// Valida um telefone brasileiro: DDD existente, celular com 9 dígitos
+/// começando com 9 ou fixo com 8 dígitos, aceitando +55 e prefixos 0xx
+/// @param string $telefone Telefone a validar
+/// @return bool true se válido
+#[php_function]
+#[php(name = "Wead\\validar_telefone")]
+pub fn validar_telefone(telefone: String) -> bool {
+    Telefone::analisar(&telefone).is_ok()
+}
+
+/// Analisa um telefone brasileiro
+/// @param string $telefone Telefone com ou sem formatação
+/// @return array ['ddd' => int, 'numero' => string, 'tipo' => 'celular'|'fixo',
+///   'e164' => string, 'nacional' => string, 'digitos' => string]
+/// @throws ValidacaoException Se o telefone for inválido (a mensagem diz o motivo)
+#[php_function]
+#[php(name = "Wead\\analisar_telefone")]
+pub fn analisar_telefone(telefone: String) -> PhpResult<ZBox<ZendHashTable>> {
+    let telefone = analisar_telefone_ou_erro(&telefone)?;
+
+    let mut resultado = ZendHashTable::new();
+    resultado.insert("ddd", telefone.ddd as i64)?;
+    resultado.insert("numero", telefone.numero.clone())?;
+    resultado.insert("tipo", if telefone.celular() { "celular" } else { "fixo" })?;
+    resultado.insert("e164", telefone.formatar(FormatoTelefone::E164))?;
+    resultado.insert("nacional", telefone.formatar(FormatoTelefone::Nacional))?;
+    resultado.insert("digitos", telefone.formatar(FormatoTelefone::Digitos))?;
+    Ok(resultado)
+}
+
+/// Analisa um telefone, convertendo o motivo da recusa em ValidacaoException
+fn analisar_telefone_ou_erro(telefone: &str) -> PhpResult<Telefone> {
+    Telefone::analisar(telefone)
+        .map_err(|motivo| ValidacaoException::erro("telefone", format!("Telefone inválido: {}", motivo)))
+}
+
+/// Valida um email segundo as RFCs 5322 (sintaxe) e 5321 (limites de
+/// tamanho), aceitando domínios internacionais
 /// @param string $email Email a validar
 /// @return bool true se válido
 #[php_function]
 #[php(name = "Wead\\validar_email")]
 pub fn validar_email(email: String) -> bool {
-    email.contains('@') && email.contains('.') && email.len() > 5
+    EnderecoEmail::analisar(&email).is_ok()
+}
+
+/// Separa um email em parte local e domínio, normalizando o domínio
+/// @param string $email Email a analisar
+/// @return array ['local' => string, 'dominio' => string (ASCII/punycode, minúsculo),
+///   'dominio_unicode' => string, 'email' => string (normalizado)]
+/// @throws ValidacaoException Se o email for inválido (a mensagem diz o motivo)
+#[php_function]
+#[php(name = "Wead\\analisar_email")]
+pub fn analisar_email(email: String) -> PhpResult<ZBox<ZendHashTable>> {
+    let endereco = EnderecoEmail::analisar(&email).map_err(|motivo| {
+        ValidacaoException::erro("email", format!("Email inválido: {}", motivo))
+    })?;
+
+    let mut resultado = ZendHashTable::new();
+    resultado.insert("local", endereco.local.clone())?;
+    resultado.insert("dominio", endereco.dominio.clone())?;
+    resultado.insert("dominio_unicode", endereco.dominio_unicode.clone())?;
+    resultado.insert("email", endereco.normalizado())?;
+    Ok(resultado)
+}
+
+/// Valida um CPF (dígitos verificadores), com ou sem pontuação
+/// @param string $cpf CPF a validar
+/// @return bool true se válido
+#[php_function]
+#[php(name = "Wead\\validar_cpf")]
+pub fn validar_cpf(cpf: String) -> bool {
+    Documento::analisar_cpf(&cpf).is_ok()
+}
+
+/// Valida um CNPJ numérico ou alfanumérico (dígitos verificadores), com
+/// ou sem pontuação
+/// @param string $cnpj CNPJ a validar
+/// @return bool true se válido
+#[php_function]
+#[php(name = "Wead\\validar_cnpj")]
+pub fn validar_cnpj(cnpj: String) -> bool {
+    Documento::analisar_cnpj(&cnpj).is_ok()
+}
+
+/// Formata um CPF ou CNPJ com a pontuação oficial
+/// (`111.444.777-35`, `12.ABC.345/01DE-35`)
+/// @param string $documento CPF ou CNPJ, com ou sem pontuação
+/// @return string Documento formatado
+/// @throws ValidacaoException Se o documento for inválido
+#[php_function]
+#[php(name = "Wead\\formatar_documento")]
+pub fn formatar_documento(documento: String) -> PhpResult<String> {
+    Ok(analisar_documento_ou_erro(&documento)?.formatado())
+}
+
+/// Formata um CPF ou CNPJ ocultando parte dos caracteres, para exibição
+/// (`***.444.777-**`, `**.ABC.345/****-**`)
+/// @param string $documento CPF ou CNPJ, com ou sem pontuação
+/// @return string Documento mascarado
+/// @throws ValidacaoException Se o documento for inválido
+#[php_function]
+#[php(name = "Wead\\mascarar_documento")]
+pub fn mascarar_documento(documento: String) -> PhpResult<String> {
+    Ok(analisar_documento_ou_erro(&documento)?.mascarado())
+}
+
+/// Analisa um CPF ou CNPJ, convertendo o motivo da recusa em ValidacaoException
+fn analisar_documento_ou_erro(documento: &str) -> PhpResult<Documento> {
+    Documento::analisar(documento)
+        .map_err(|motivo| ValidacaoException::erro("documento", format!("Documento inválido: {}", motivo)))
 }
 
 /// Gera um resumo de uma pessoa em formato legível
@@ -601,8 +1518,98 @@ pub fn register(module: ModuleBuilder) -> ModuleBuilder {
         .class::<InterfacePersistivel>()
         .class::<EntidadeBase>()
         .class::<Pessoa>()
+        .class::<WeadException>()
+        .class::<ValidacaoException>()
+        .class::<NaoEncontradoException>()
+        .class::<ArmazenamentoException>()
+        .class::<BloqueioException>()
+        .class::<DuplicidadeException>()
+        .class::<ConflitoException>()
         .class::<Storage>()
+        .class::<IteradorPessoas>()
+        .class::<Consulta>()
+        .class::<Alteracao>()
+        .class::<RegrasValidacao>()
         .function(wrap_function!(formatar_telefone))
+        .function(wrap_function!(validar_telefone))
+        .function(wrap_function!(analisar_telefone))
         .function(wrap_function!(validar_email))
+        .function(wrap_function!(analisar_email))
+        .function(wrap_function!(validar_cpf))
+        .function(wrap_function!(validar_cnpj))
+        .function(wrap_function!(formatar_documento))
+        .function(wrap_function!(mascarar_documento))
         .function(wrap_function!(resumo_pessoa))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// DSNs de cada backend, com arquivos num diretório temporário exclusivo
+    fn dsns(nome: &str) -> Vec<String> {
+        let diretorio = std::env::temp_dir().join(format!("wead_storage_{}_{}", std::process::id(), nome));
+        let _ = std::fs::remove_dir_all(&diretorio);
+        std::fs::create_dir_all(&diretorio).unwrap();
+
+        let mut dsns = vec![
+            "memory:".to_string(),
+            format!("csv://{}", diretorio.join("a.csv").display()),
+            format!("jsonl://{}", diretorio.join("a.jsonl").display()),
+        ];
+        if cfg!(feature = "sqlite") {
+            dsns.push(format!("sqlite://{}", diretorio.join("b.db").display()));
+        }
+        dsns
+    }
+
+    fn nomes(pessoas: &[Pessoa]) -> Vec<&str> {
+        pessoas.iter().map(|p| p.nome.as_str()).collect()
+    }
+
+    #[test]
+    fn exclusao_logica_e_restauracao() {
+        for dsn in dsns("exclusao") {
+            let mut storage = Storage::__construct(dsn.clone()).unwrap();
+            storage.criar(&mut Pessoa::__construct("Ana".into(), "ana@x.com".into(), "1".into())).unwrap();
+            storage.criar(&mut Pessoa::__construct("Bia".into(), "bia@x.com".into(), "1".into())).unwrap();
+            let versao = storage.buscar_por_id(1).unwrap().unwrap().versao;
+
+            assert!(storage.deletar(1).unwrap());
+            assert!(storage.deletar(1).is_err(), "{}", dsn);
+            assert_eq!(nomes(&storage.listar_todas().unwrap()), ["Bia"], "{}", dsn);
+            assert_eq!(storage.listar_incluindo_deletados().unwrap().len(), 2, "{}", dsn);
+            assert!(storage.buscar_por_id(1).unwrap().is_none());
+            assert_eq!(storage.contar().unwrap(), 1, "{}", dsn);
+
+            // A marca de exclusão é persistida
+            if !dsn.starts_with("memory") {
+                let reaberto = Storage::__construct(dsn.clone()).unwrap();
+                let ana = reaberto.buscar_qualquer(1).unwrap().unwrap();
+                assert!(ana.esta_deletada(), "{}", dsn);
+                assert_eq!(ana.versao, versao + 1, "{}", dsn);
+            }
+
+            assert!(storage.restaurar(1).unwrap());
+            assert!(!storage.restaurar(1).unwrap());
+            assert_eq!(nomes(&storage.listar_todas().unwrap()), ["Ana", "Bia"], "{}", dsn);
+            assert_eq!(storage.buscar_por_id(1).unwrap().unwrap().versao, versao + 2, "{}", dsn);
+        }
+    }
+
+    #[test]
+    fn expurgo_remove_de_vez() {
+        for dsn in dsns("expurgo") {
+            let mut storage = Storage::__construct(dsn.clone()).unwrap();
+            storage.criar(&mut Pessoa::__construct("Ana".into(), "ana@x.com".into(), "1".into())).unwrap();
+            storage.criar(&mut Pessoa::__construct("Bia".into(), "bia@x.com".into(), "1".into())).unwrap();
+
+            storage.deletar(2).unwrap();
+            assert!(storage.expurgar(2).unwrap());
+            assert!(storage.expurgar(1).unwrap());
+            assert!(storage.listar_incluindo_deletados().unwrap().is_empty(), "{}", dsn);
+            assert!(storage.restaurar(2).is_err(), "{}", dsn);
+            assert!(storage.expurgar(2).is_err(), "{}", dsn);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// ============================================================================
+// ESCRITA ATÔMICA
+// Substitui arquivos sem nunca deixar o original truncado ou pela metade
+// ============================================================================
+
+/// Reescreve `caminho` de forma atômica e resistente a falhas
+///
+/// O conteúdo é gravado em um arquivo temporário no mesmo diretório,
+/// sincronizado em disco e então renomeado sobre o original. Por fim o
+/// diretório também é sincronizado para que a troca sobreviva a uma queda.
+/// Se algo falhar no meio do caminho o arquivo original continua intacto.
+pub fn reescrever<F>(caminho: &Path, escrever: F) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let (temporario, arquivo) = criar_temporario(caminho)?;
+
+    let resultado = (|| {
+        let mut writer = BufWriter::new(arquivo);
+        escrever(&mut writer)?;
+
+        let arquivo = writer.into_inner().map_err(|e| e.into_error())?;
+        arquivo.sync_all()?;
+        drop(arquivo);
+
+        fs::rename(&temporario, caminho)?;
+        sincronizar_diretorio(caminho)
+    })();
+
+    if resultado.is_err() {
+        let _ = fs::remove_file(&temporario);
+    }
+    resultado
+}
+
+/// Contador das reescritas deste processo, para que threads (PHP ZTS)
+/// reescrevendo o mesmo arquivo nunca usem o mesmo temporário
+static REESCRITAS: AtomicU64 = AtomicU64::new(0);
+
+/// Cria um arquivo temporário novo (nunca um já existente, ex.: deixado
+/// por uma queda) no mesmo diretório do original
+fn criar_temporario(caminho: &Path) -> io::Result<(PathBuf, File)> {
+    loop {
+        let temporario = caminho_temporario(caminho, REESCRITAS.fetch_add(1, Ordering::Relaxed));
+        match OpenOptions::new().write(true).create_new(true).open(&temporario) {
+            Ok(arquivo) => return Ok((temporario, arquivo)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Caminho do arquivo temporário (oculto, no mesmo diretório do original),
+/// único por processo e reescrita
+fn caminho_temporario(caminho: &Path, reescrita: u64) -> PathBuf {
+    let nome = caminho
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    caminho.with_file_name(format!(".{}.tmp-{}-{}", nome, std::process::id(), reescrita))
+}
+
+/// Sincroniza o diretório que contém `caminho` (garante a persistência do rename)
+#[cfg(unix)]
+fn sincronizar_diretorio(caminho: &Path) -> io::Result<()> {
+    let diretorio = match caminho.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    File::open(diretorio)?.sync_all()
+}
+
+/// Fora do Unix não há como abrir um diretório para sincronizá-lo
+#[cfg(not(unix))]
+fn sincronizar_diretorio(_caminho: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Diretório temporário exclusivo do teste
+    fn diretorio(nome: &str) -> PathBuf {
+        let diretorio = std::env::temp_dir().join(format!("wead_atomico_{}_{}", std::process::id(), nome));
+        let _ = fs::remove_dir_all(&diretorio);
+        fs::create_dir_all(&diretorio).unwrap();
+        diretorio
+    }
+
+    /// Arquivos do diretório, além do próprio `caminho`
+    fn sobras(diretorio: &Path) -> Vec<String> {
+        fs::read_dir(diretorio)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|nome| nome != "dados.txt")
+            .collect()
+    }
+
+    #[test]
+    fn falha_mantem_o_original() {
+        let diretorio = diretorio("falha");
+        let caminho = diretorio.join("dados.txt");
+        fs::write(&caminho, "original").unwrap();
+
+        let resultado = reescrever(&caminho, |w| {
+            w.write_all(b"pela metade")?;
+            Err(io::Error::other("disco cheio"))
+        });
+
+        assert!(resultado.is_err());
+        assert_eq!(fs::read_to_string(&caminho).unwrap(), "original");
+        assert!(sobras(&diretorio).is_empty());
+    }
+
+    #[test]
+    fn threads_nao_compartilham_o_temporario() {
+        let diretorio = diretorio("threads");
+        let caminho = diretorio.join("dados.txt");
+        let conteudos: Vec<String> = (0..8).map(|i| format!("{}\n", i).repeat(10_000)).collect();
+
+        thread::scope(|escopo| {
+            for conteudo in &conteudos {
+                let caminho = &caminho;
+                escopo.spawn(move || {
+                    for _ in 0..5 {
+                        reescrever(caminho, |w| w.write_all(conteudo.as_bytes())).unwrap();
+                    }
+                });
+            }
+        });
+
+        // O arquivo final é uma das versões inteiras, nunca uma mistura
+        assert!(conteudos.contains(&fs::read_to_string(&caminho).unwrap()));
+        assert!(sobras(&diretorio).is_empty());
+    }
+
+    #[test]
+    fn temporario_deixado_por_queda_nao_e_reaproveitado() {
+        let diretorio = diretorio("queda");
+        let caminho = diretorio.join("dados.txt");
+        let proximo = REESCRITAS.load(Ordering::Relaxed);
+        let abandonado = caminho_temporario(&caminho, proximo);
+        fs::write(&abandonado, "lixo").unwrap();
+
+        reescrever(&caminho, |w| w.write_all(b"novo")).unwrap();
+        assert_eq!(fs::read_to_string(&caminho).unwrap(), "novo");
+        assert_eq!(fs::read_to_string(&abandonado).unwrap(), "lixo");
+    }
+}
@@ -0,0 +1,509 @@
+mod arquivo;
+mod memoria;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use ext_php_rs::prelude::*;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::time::Duration;
+
+use super::csv::FormatoCsv;
+use super::historico::Alteracao;
+use super::jsonl::FormatoJsonl;
+use super::{ConflitoException, DuplicidadeException, NaoEncontradoException, Pessoa, ValidacaoException};
+
+pub use arquivo::{
+    ArquivoBackend, Entrada, Esquema, Formato, LinhaInvalida, LIMITE_COMPACTACAO_PADRAO,
+};
+pub use memoria::MemoriaBackend;
+
+/// Versão atual do esquema dos registros de pessoa
+/// 1. `id`, `nome`, `email`, `telefone`
+/// 2. mais `deletado_em` (exclusão lógica)
+/// 3. mais `versao` (controle de concorrência)
+/// 4. mais `documento` (CPF ou CNPJ, opcional)
+pub const VERSAO_ESQUEMA: i64 = 4;
+
+/// Sequência de pessoas lida sob demanda, um registro por vez
+pub type FluxoPessoas = Box<dyn Iterator<Item = PhpResult<Pessoa>>>;
+
+/// Operação pendente de uma transação do Storage
+#[derive(Debug, Clone)]
+pub enum Operacao {
+    /// Inserção de uma pessoa com ID já reservado
+    Criar(Pessoa),
+
+    /// Substituição da pessoa com o mesmo ID
+    Atualizar(Pessoa),
+
+    /// Remoção do ID informado (ignorada se ele já não existir)
+    Deletar(i64),
+
+    /// Remoção de todos os registros
+    Limpar,
+}
+
+/// Resultado da verificação de integridade dos dados gravados
+#[derive(Debug, Default)]
+pub struct Verificacao {
+    /// Pessoas legíveis (inclusive deletadas), como o modo tolerante as lê
+    pub pessoas: Vec<Pessoa>,
+
+    /// Linhas que não são registros válidos (linha 0 = erro que impediu
+    /// ler o restante dos dados)
+    pub linhas_invalidas: Vec<LinhaInvalida>,
+
+    /// IDs usados por mais de um registro, na ordem em que foram achados
+    pub ids_duplicados: Vec<i64>,
+}
+
+// ============================================================================
+// TRAIT: StorageBackend
+// Contrato de armazenamento usado pela classe Wead\Storage
+// ============================================================================
+
+/// Backend de armazenamento de pessoas
+///
+/// A classe `Wead\Storage` valida os dados e traduz os resultados em
+/// exceções PHP; o backend só precisa persistir e recuperar registros.
+/// Para adicionar um novo formato basta implementar este trait e
+/// registrá-lo em [`abrir`].
+///
+/// Toda escrita (exceto `substituir_todos`, usada em migrações) acrescenta ao
+/// histórico as alterações que fez, sob o mesmo bloqueio ou transação: se o
+/// histórico não puder ser gravado, a escrita falha sem alterar os dados.
+pub trait StorageBackend: Debug {
+    /// Insere uma nova pessoa, atribuindo a ela um ID único
+    fn criar(&mut self, pessoa: &mut Pessoa) -> PhpResult<i64>;
+
+    /// Insere várias pessoas já validadas com IDs consecutivos; retorna os IDs
+    /// As criações entram no histórico junto com os registros
+    /// A implementação padrão reserva a faixa e grava tudo com `aplicar`
+    fn criar_varios(&mut self, pessoas: &mut [Pessoa]) -> PhpResult<Vec<i64>> {
+        let primeiro = self.reservar_ids(pessoas.len() as i64)?;
+        let ids: Vec<i64> = (primeiro..).take(pessoas.len()).collect();
+
+        for (pessoa, id) in pessoas.iter_mut().zip(&ids) {
+            pessoa.definir_id(*id);
+        }
+
+        self.aplicar(pessoas.iter().cloned().map(Operacao::Criar).collect())?;
+        Ok(ids)
+    }
+
+    /// Substitui a pessoa com o mesmo ID; retorna `false` se não existir
+    /// A versão recebida deve ser a seguinte à armazenada (ConflitoException
+    /// caso contrário), conferida sob o bloqueio de escrita
+    fn atualizar(&mut self, pessoa: &Pessoa) -> PhpResult<bool>;
+
+    /// Remove a pessoa com o ID informado; retorna `false` se não existir
+    fn deletar(&mut self, id: i64) -> PhpResult<bool>;
+
+    /// Lista todas as pessoas armazenadas, inclusive as deletadas (soft delete)
+    fn listar(&self) -> PhpResult<Vec<Pessoa>>;
+
+    /// Remove todos os registros, expurgando cada um no histórico
+    fn limpar(&mut self) -> PhpResult {
+        self.aplicar(vec![Operacao::Limpar])
+    }
+
+    /// Substitui todo o conteúdo pelas pessoas informadas, preservando IDs
+    /// Pessoas sem ID recebem um novo; a sequência nunca retrocede
+    fn substituir_todos(&mut self, pessoas: Vec<Pessoa>) -> PhpResult;
+
+    /// Reserva `quantidade` IDs consecutivos e retorna o primeiro
+    /// IDs reservados e não usados (ex.: transação desfeita) são descartados
+    fn reservar_ids(&mut self, quantidade: i64) -> PhpResult<i64>;
+
+    /// Caminho ou identificação do local de armazenamento
+    fn caminho(&self) -> String;
+
+    /// Histórico de alterações de uma pessoa, em ordem cronológica
+    /// (nunca é compactado nem limpo)
+    fn historico(&self, id: i64) -> PhpResult<Vec<Alteracao>>;
+
+    /// Percorre as pessoas sob demanda; as mesmas de `listar`, na mesma ordem
+    /// A implementação padrão carrega tudo (adequada a backends em memória)
+    fn iterar(&self) -> PhpResult<FluxoPessoas> {
+        Ok(Box::new(self.listar()?.into_iter().map(Ok)))
+    }
+
+    /// Busca uma pessoa pelo ID
+    fn buscar(&self, id: i64) -> PhpResult<Option<Pessoa>> {
+        Ok(self.listar()?.into_iter().find(|p| p.id == Some(id)))
+    }
+
+    /// Conta as pessoas armazenadas que não estão deletadas (soft delete)
+    fn contar(&self) -> PhpResult<i64> {
+        Ok(self.listar()?.iter().filter(|p| p.deletado_em.is_none()).count() as i64)
+    }
+
+    /// Aplica as operações de uma vez: ou todas são gravadas, ou nenhuma
+    /// O histórico é calculado sobre o estado lido sob o mesmo bloqueio (ou
+    /// transação) da gravação e gravado junto com ela: se um falhar, nada muda
+    fn aplicar(&mut self, operacoes: Vec<Operacao>) -> PhpResult;
+
+    /// Descarta versões obsoletas e remoções; retorna quantas entradas saíram
+    /// Backends que não acumulam histórico não têm o que compactar
+    fn compactar(&mut self) -> PhpResult<i64> {
+        Ok(0)
+    }
+
+    /// Liga ou desliga a restrição de email único em `criar`, `criar_varios`,
+    /// `atualizar` e `aplicar` (a verificação é feita sob o bloqueio de escrita)
+    fn definir_email_unico(&mut self, ativo: bool);
+
+    /// Define quem faz as próximas alterações, gravado no histórico
+    fn definir_ator(&mut self, ator: Option<String>);
+
+    /// Versão do esquema em que os dados estão gravados
+    /// Backends sem esquema próprio estão sempre na versão atual
+    fn versao_esquema(&self) -> PhpResult<i64> {
+        Ok(VERSAO_ESQUEMA)
+    }
+
+    /// Converte os dados gravados para o esquema atual; retorna `false`
+    /// se eles já estavam nele
+    fn migrar_esquema(&mut self) -> PhpResult<bool> {
+        Ok(false)
+    }
+
+    /// Confere a integridade dos dados sem interromper nos problemas
+    /// A implementação padrão só procura IDs repetidos em `listar`
+    fn verificar(&self) -> PhpResult<Verificacao> {
+        let pessoas = self.listar()?;
+        let mut vistos = HashSet::new();
+        let mut ids_duplicados = Vec::new();
+
+        for id in pessoas.iter().filter_map(|p| p.id) {
+            if !vistos.insert(id) && !ids_duplicados.contains(&id) {
+                ids_duplicados.push(id);
+            }
+        }
+
+        Ok(Verificacao {
+            pessoas,
+            linhas_invalidas: Vec::new(),
+            ids_duplicados,
+        })
+    }
+
+    /// Liga ou desliga o modo estrito de leitura: linhas malformadas geram
+    /// erro em vez de serem ignoradas (ignorado por quem não lê texto)
+    fn definir_modo_estrito(&mut self, _ativo: bool) {}
+
+    /// Ajusta o tempo de espera por bloqueios (ignorado por quem não bloqueia)
+    fn definir_tempo_limite_bloqueio(&mut self, _tempo_limite: Duration) {}
+
+    /// Ajusta a razão de lixo que dispara a compactação automática
+    /// (ignorado por quem não acumula histórico)
+    fn definir_limite_compactacao(&mut self, _razao: f64) {}
+}
+
+/// Aplica operações pendentes sobre uma lista de pessoas em memória
+/// Falha se uma atualização apontar para um ID que não existe mais ou
+/// para uma versão que já foi alterada por outra operação
+pub fn aplicar_operacoes(pessoas: &mut Vec<Pessoa>, operacoes: &[Operacao]) -> PhpResult {
+    for operacao in operacoes {
+        match operacao {
+            Operacao::Criar(pessoa) => pessoas.push(pessoa.clone()),
+            Operacao::Atualizar(pessoa) => {
+                match pessoas.iter_mut().find(|p| p.id == pessoa.id) {
+                    Some(p) => {
+                        verificar_versao(p, pessoa)?;
+                        *p = pessoa.clone();
+                    }
+                    None => return Err(nao_encontrada(pessoa.id.unwrap_or(0))),
+                }
+            }
+            Operacao::Deletar(id) => pessoas.retain(|p| p.id != Some(*id)),
+            Operacao::Limpar => pessoas.clear(),
+        }
+    }
+    Ok(())
+}
+
+/// Garante que nenhuma das pessoas `novas` use o email de outra pessoa
+/// Registros de `existentes` com o mesmo ID de uma nova são ignorados,
+/// pois estão sendo substituídos por ela
+pub fn verificar_emails_unicos(existentes: &[Pessoa], novas: &[Pessoa]) -> PhpResult {
+    let substituidas: HashSet<i64> = novas.iter().filter_map(|p| p.id).collect();
+    let mut emails: HashMap<String, Option<i64>> = existentes
+        .iter()
+        .filter(|p| !p.id.is_some_and(|id| substituidas.contains(&id)))
+        .map(|p| (normalizar_email(&p.email), p.id))
+        .collect();
+
+    for nova in novas {
+        match emails.entry(normalizar_email(&nova.email)) {
+            Entry::Occupied(dono) => return Err(email_duplicado(&nova.email, *dono.get())),
+            Entry::Vacant(livre) => {
+                livre.insert(nova.id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Verifica a restrição de email único sobre o resultado de `aplicar_operacoes`,
+/// considerando apenas as pessoas criadas ou atualizadas pelas operações
+pub fn verificar_operacoes(pessoas: &[Pessoa], operacoes: &[Operacao]) -> PhpResult {
+    let alteradas: HashSet<i64> = operacoes
+        .iter()
+        .filter_map(|operacao| match operacao {
+            Operacao::Criar(p) | Operacao::Atualizar(p) => p.id,
+            _ => None,
+        })
+        .collect();
+
+    let novas: Vec<Pessoa> = pessoas
+        .iter()
+        .filter(|p| p.id.is_some_and(|id| alteradas.contains(&id)))
+        .cloned()
+        .collect();
+
+    verificar_emails_unicos(pessoas, &novas)
+}
+
+/// Forma canônica de um email para comparação (sem espaços, minúsculo)
+pub fn normalizar_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Erro de email já usado por outra pessoa
+pub fn email_duplicado(email: &str, id_dono: Option<i64>) -> PhpException {
+    DuplicidadeException::erro(mensagem_email_duplicado(email, id_dono))
+}
+
+/// Mensagem de email já usado por outra pessoa
+pub fn mensagem_email_duplicado(email: &str, id_dono: Option<i64>) -> String {
+    let dono = id_dono.map(|id| format!(" (ID {})", id)).unwrap_or_default();
+    format!("Email já cadastrado: {}{}", email.trim(), dono)
+}
+
+/// Garante que `nova` é a versão seguinte à `armazenada`
+pub fn verificar_versao(armazenada: &Pessoa, nova: &Pessoa) -> PhpResult {
+    if armazenada.versao + 1 != nova.versao {
+        return Err(conflito_versao(nova.id.unwrap_or(0), nova.versao - 1, armazenada.versao));
+    }
+    Ok(())
+}
+
+/// Erro de atualização sobre uma versão que não é mais a armazenada
+pub fn conflito_versao(id: i64, esperada: i64, atual: i64) -> PhpException {
+    ConflitoException::erro(format!(
+        "Pessoa com ID {} foi alterada por outra operação (versão esperada {}, atual {})",
+        id, esperada, atual
+    ))
+}
+
+/// Erro padrão para um ID inexistente
+pub fn nao_encontrada(id: i64) -> PhpException {
+    NaoEncontradoException::erro(id)
+}
+
+/// Abre o backend indicado por um DSN
+///
+/// Formatos aceitos:
+/// - `csv:///caminho/arquivo.csv`
+/// - `jsonl:///caminho/arquivo.jsonl`
+/// - `memory:` (privado da instância) ou `memory:nome` (compartilhado na
+///   thread e mantido entre requisições atendidas pelo mesmo worker)
+/// - `sqlite:///caminho/banco.db[?csv=/caminho/origem.csv]` (feature "sqlite")
+/// - `/caminho/arquivo.csv` (sem esquema, equivale a `csv://`)
+pub fn abrir(dsn: &str, tempo_limite_bloqueio: Duration) -> PhpResult<Box<dyn StorageBackend>> {
+    let (esquema, caminho) = separar_dsn(dsn);
+
+    if esquema == "memory" {
+        return Ok(Box::new(MemoriaBackend::new(caminho)));
+    }
+
+    if caminho.trim().is_empty() {
+        return Err(ValidacaoException::erro(
+            "dsn",
+            "Caminho do arquivo não pode ser vazio".into()
+        ));
+    }
+
+    match esquema {
+        "csv" => Ok(Box::new(ArquivoBackend::new(
+            caminho,
+            Box::new(FormatoCsv),
+            tempo_limite_bloqueio,
+        )?)),
+        "jsonl" => Ok(Box::new(ArquivoBackend::new(
+            caminho,
+            Box::new(FormatoJsonl),
+            tempo_limite_bloqueio,
+        )?)),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => {
+            let (caminho, csv_origem) = match caminho.split_once("?csv=") {
+                Some((caminho, origem)) => (caminho, Some(origem)),
+                None => (caminho, None),
+            };
+            Ok(Box::new(sqlite::SqliteBackend::new(
+                caminho,
+                csv_origem,
+                tempo_limite_bloqueio,
+            )?))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        "sqlite" => Err(ValidacaoException::erro(
+            "dsn",
+            "Backend sqlite indisponível: compile a extensão com --features sqlite".into()
+        )),
+        outro => Err(ValidacaoException::erro(
+            "dsn",
+            format!("Backend de armazenamento não suportado: {}", outro)
+        )),
+    }
+}
+
+/// Esquemas de DSN conhecidos (aceitos também sem `//`, ex.: `memory:nome`)
+const ESQUEMAS: [&str; 4] = ["csv", "jsonl", "memory", "sqlite"];
+
+/// Separa o esquema do restante do DSN
+/// O prefixo só é esquema se for conhecido ou vier seguido de `://`
+/// (um esquema desconhecido é recusado por `abrir`); do contrário o DSN é
+/// um caminho CSV, mesmo com `:` (ex.: `backup:2024.csv` ou `C:\dados\a.csv`)
+fn separar_dsn(dsn: &str) -> (&str, &str) {
+    let Some((esquema, resto)) = dsn.split_once(':') else {
+        return ("csv", dsn);
+    };
+
+    if ESQUEMAS.contains(&esquema) {
+        return (esquema, resto.strip_prefix("//").unwrap_or(resto));
+    }
+    match resto.strip_prefix("//") {
+        Some(caminho) if esquema.len() > 1 && esquema.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            (esquema, caminho)
+        }
+        _ => ("csv", dsn),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn esquemas_conhecidos() {
+        assert_eq!(separar_dsn("csv:///tmp/a.csv"), ("csv", "/tmp/a.csv"));
+        assert_eq!(separar_dsn("jsonl://a.jsonl"), ("jsonl", "a.jsonl"));
+        assert_eq!(separar_dsn("memory:"), ("memory", ""));
+        assert_eq!(separar_dsn("memory:nome"), ("memory", "nome"));
+        assert_eq!(separar_dsn("sqlite:///tmp/a.db?csv=/tmp/a.csv"), ("sqlite", "/tmp/a.db?csv=/tmp/a.csv"));
+    }
+
+    #[test]
+    fn caminhos_com_dois_pontos() {
+        assert_eq!(separar_dsn("/tmp/a.csv"), ("csv", "/tmp/a.csv"));
+        assert_eq!(separar_dsn("backup:2024.csv"), ("csv", "backup:2024.csv"));
+        assert_eq!(separar_dsn("C:\\dados\\a.csv"), ("csv", "C:\\dados\\a.csv"));
+    }
+
+    #[test]
+    fn esquema_desconhecido_com_barras() {
+        assert_eq!(separar_dsn("redis://localhost"), ("redis", "localhost"));
+    }
+
+    /// Pessoa com ID, nome e email derivado do ID, na versão 0
+    fn pessoa(id: i64, nome: &str) -> Pessoa {
+        let mut pessoa = Pessoa::__construct(nome.into(), format!("{}@x.com", id), "1".into());
+        pessoa.definir_id(id);
+        pessoa
+    }
+
+    /// Nova versão de `pessoa` com outro nome
+    fn renomeada(pessoa: &Pessoa, nome: &str) -> Pessoa {
+        let mut nova = pessoa.clone();
+        nova.nome = nome.into();
+        nova.versao += 1;
+        nova
+    }
+
+    fn nomes(pessoas: &[Pessoa]) -> Vec<&str> {
+        pessoas.iter().map(|p| p.nome.as_str()).collect()
+    }
+
+    #[test]
+    fn operacoes_aplicadas_em_ordem() {
+        let ana = pessoa(1, "Ana");
+        let mut pessoas = vec![ana.clone(), pessoa(2, "Bia")];
+        let operacoes = [
+            Operacao::Criar(pessoa(3, "Cid")),
+            Operacao::Atualizar(renomeada(&ana, "Ana Maria")),
+            Operacao::Deletar(2),
+        ];
+
+        aplicar_operacoes(&mut pessoas, &operacoes).unwrap();
+        assert_eq!(nomes(&pessoas), ["Ana Maria", "Cid"]);
+        assert_eq!(pessoas[0].versao, 1);
+
+        aplicar_operacoes(&mut pessoas, &[Operacao::Limpar, Operacao::Criar(pessoa(4, "Davi"))]).unwrap();
+        assert_eq!(nomes(&pessoas), ["Davi"]);
+    }
+
+    #[test]
+    fn atualizacao_de_removida_ou_versao_antiga_falha() {
+        let ana = pessoa(1, "Ana");
+
+        let mut pessoas = vec![ana.clone()];
+        let removida = [Operacao::Deletar(1), Operacao::Atualizar(renomeada(&ana, "Ana Maria"))];
+        assert!(aplicar_operacoes(&mut pessoas, &removida).is_err());
+
+        // Duas atualizações a partir da mesma versão: a segunda conflita
+        let mut pessoas = vec![ana.clone()];
+        let concorrentes = [
+            Operacao::Atualizar(renomeada(&ana, "Ana Maria")),
+            Operacao::Atualizar(renomeada(&ana, "Ana Paula")),
+        ];
+        assert!(aplicar_operacoes(&mut pessoas, &concorrentes).is_err());
+    }
+
+    #[test]
+    fn emails_unicos_entre_as_alteradas() {
+        let mut bia = pessoa(2, "Bia");
+        bia.email = " 1@X.com ".into();
+
+        let pessoas = [pessoa(1, "Ana"), bia.clone()];
+        assert!(verificar_operacoes(&pessoas, &[Operacao::Criar(bia.clone())]).is_err());
+
+        // O email passa a outra pessoa na mesma transação em que sai da primeira
+        let pessoas = [bia.clone()];
+        assert!(verificar_operacoes(&pessoas, &[Operacao::Deletar(1), Operacao::Criar(bia)]).is_ok());
+    }
+
+    #[test]
+    fn transacao_com_conflito_nao_altera_nada() {
+        let mut backend = MemoriaBackend::new("");
+        let mut ana = Pessoa::__construct("Ana".into(), "ana@x.com".into(), "1".into());
+        backend.criar(&mut ana).unwrap();
+
+        let operacoes = vec![
+            Operacao::Criar(pessoa(2, "Bia")),
+            Operacao::Atualizar(renomeada(&ana, "Ana Maria")),
+            Operacao::Atualizar(renomeada(&ana, "Ana Paula")),
+        ];
+        assert!(backend.aplicar(operacoes).is_err());
+        assert_eq!(nomes(&backend.listar().unwrap()), ["Ana"]);
+        assert!(backend.historico(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn criar_varios_usa_ids_consecutivos() {
+        let mut backend = MemoriaBackend::new("");
+        backend.reservar_ids(5).unwrap();
+
+        let mut pessoas = vec![
+            Pessoa::__construct("Ana".into(), "ana@x.com".into(), "1".into()),
+            Pessoa::__construct("Bia".into(), "bia@x.com".into(), "1".into()),
+        ];
+        assert_eq!(backend.criar_varios(&mut pessoas).unwrap(), [6, 7]);
+        assert_eq!(pessoas[1].id, Some(7));
+        assert_eq!(backend.historico(7).unwrap()[0].operacao, "criar");
+    }
+}
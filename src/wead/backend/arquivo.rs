@@ -0,0 +1,1253 @@
+use ext_php_rs::prelude::*;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::slice;
+use std::time::Duration;
+
+use super::{
+    aplicar_operacoes, verificar_emails_unicos, verificar_operacoes, verificar_versao,
+    FluxoPessoas, Operacao, StorageBackend, Verificacao, VERSAO_ESQUEMA,
+};
+use crate::wead::atomico;
+use crate::wead::bloqueio::{Bloqueio, ErroBloqueio, ModoBloqueio};
+use crate::wead::historico::{alteracoes_das_operacoes, Alteracao};
+use crate::wead::{ArmazenamentoException, BloqueioException, Pessoa};
+
+// ============================================================================
+// BACKEND: ArquivoBackend
+// Armazenamento em arquivo texto no formato de log (somente acréscimo)
+// ============================================================================
+
+/// Razão de lixo padrão que dispara a compactação automática
+pub const LIMITE_COMPACTACAO_PADRAO: f64 = 0.5;
+
+/// Entrada do log de um arquivo de pessoas
+#[derive(Debug, Clone)]
+pub enum Entrada {
+    /// Versão (inicial ou atualizada) de uma pessoa
+    Registro(Pessoa),
+
+    /// Marca de remoção (tombstone) do ID informado
+    Remocao(i64),
+
+    /// Linha que não pôde ser interpretada como registro
+    Invalida(LinhaInvalida),
+}
+
+/// Linha do arquivo que não é um registro válido
+#[derive(Debug, Clone)]
+pub struct LinhaInvalida {
+    /// Número da linha no arquivo (a partir de 1)
+    pub linha: usize,
+
+    /// Conteúdo bruto da linha, sem a quebra final
+    pub conteudo: String,
+
+    /// Descrição do problema encontrado
+    pub motivo: String,
+
+    /// Pessoa como o modo tolerante a aproveita (None = linha ignorada)
+    pub aproveitavel: Option<Pessoa>,
+}
+
+/// Formato de serialização de um arquivo de pessoas (CSV, JSON Lines...)
+pub trait Formato: Debug {
+    /// Conteúdo inicial de um arquivo novo (ex.: cabeçalho do CSV)
+    fn cabecalho(&self) -> String;
+
+    /// Serializa uma pessoa como registro completo, com quebra de linha final
+    fn codificar(&self, pessoa: &Pessoa) -> String;
+
+    /// Serializa a marca de remoção de um ID, com quebra de linha final
+    fn codificar_remocao(&self, id: i64) -> String;
+
+    /// Lê as entradas de um arquivo aberto desde o início
+    /// Linhas malformadas viram `Entrada::Invalida`; um `Err` é um problema
+    /// que impede continuar a leitura (ex.: aspas não fechadas no fim)
+    fn ler(&self, leitor: Box<dyn BufRead>) -> Box<dyn Iterator<Item = io::Result<Entrada>>>;
+
+    /// Confere o esquema de um arquivo existente (cabeçalho, versão...)
+    /// Formatos sem cabeçalho estão sempre no esquema atual
+    fn esquema(&self, _leitor: Box<dyn BufRead>) -> io::Result<Esquema> {
+        Ok(Esquema {
+            versao: VERSAO_ESQUEMA,
+            atual: true,
+            colunas_extras: Vec::new(),
+        })
+    }
+}
+
+/// Situação do esquema de um arquivo existente
+#[derive(Debug, Clone)]
+pub struct Esquema {
+    /// Versão declarada no arquivo (ou deduzida, em arquivos antigos)
+    pub versao: i64,
+
+    /// Se o arquivo já está exatamente no formato gravado por `codificar`
+    /// (só então é seguro acrescentar entradas sem migrá-lo)
+    pub atual: bool,
+
+    /// Colunas desconhecidas, que a migração descarta
+    pub colunas_extras: Vec<String>,
+}
+
+/// Entradas de um arquivo lidas sob demanda
+type FluxoEntradas = Box<dyn Iterator<Item = PhpResult<Entrada>>>;
+
+/// Estado do arquivo após aplicar todas as entradas do log
+#[derive(Debug, Default)]
+struct Leitura {
+    /// Versão mais recente de cada pessoa não removida, na ordem de criação
+    pessoas: Vec<Pessoa>,
+
+    /// Total de entradas no arquivo (vigentes + obsoletas)
+    entradas: usize,
+
+    /// Maior ID que já apareceu no arquivo, inclusive removidos
+    maior_id: i64,
+}
+
+impl Leitura {
+    /// Fração das entradas que já não representa nenhum dado vigente
+    fn razao_lixo(&self) -> f64 {
+        if self.entradas == 0 {
+            return 0.0;
+        }
+        1.0 - self.pessoas.len() as f64 / self.entradas as f64
+    }
+}
+
+/// Índice montado pela primeira passada de `iterar`
+#[derive(Debug, Default)]
+struct IndiceLog {
+    /// Bytes do início do arquivo antes do primeiro registro (marcador
+    /// e cabeçalho), necessários para ler a partir de qualquer registro
+    cabecalho: Vec<u8>,
+
+    /// Deslocamento (em bytes) da versão mais recente de cada pessoa
+    /// vigente, na ordem de `listar`
+    deslocamentos: Vec<u64>,
+}
+
+/// Backend baseado em arquivo, seguro entre processos
+///
+/// - Leituras usam bloqueio compartilhado e escritas bloqueio exclusivo
+/// - Atualizações e remoções só acrescentam entradas ao fim do arquivo;
+///   a leitura resolve a versão mais recente de cada ID
+/// - Compactação (manual ou automática) e demais reescritas são atômicas
+/// - Um registro cortado no fim do arquivo (queda no meio de uma escrita,
+///   que nunca foi confirmada) é descartado antes do próximo acréscimo
+/// - O próximo ID é o maior já visto no arquivo (inclusive removidos) ou
+///   em `<arquivo>.seq`, mais um; a sequência só é gravada quando IDs
+///   saem do arquivo sem terem sido gravados nele (reservas) ou deixam de
+///   aparecer nele (reescritas que descartam o maior ID), então uma
+///   criação comum não a toca
+/// - O histórico de alterações fica em `<arquivo>.historico` (JSON Lines),
+///   que a compactação não toca
+/// - O esquema é validado na abertura; um arquivo em esquema antigo é
+///   migrado (reescrito) antes da primeira escrita que não o reescreve
+///   por inteiro, exceto se tiver colunas extras: aí a migração precisa
+///   ser pedida com `migrar_esquema`, pois elas seriam descartadas
+/// - `iterar` lê o arquivo em duas passadas: a primeira guarda só o
+///   deslocamento da versão mais recente de cada ID, nunca o conteúdo dos
+///   registros; a segunda emite as pessoas na ordem de `listar`, lendo
+///   em sequência e saltando (seek) só para versões gravadas em outro ponto
+#[derive(Debug)]
+pub struct ArquivoBackend {
+    /// Caminho do arquivo de dados
+    caminho: PathBuf,
+
+    /// Formato dos registros
+    formato: Rc<dyn Formato>,
+
+    /// Tempo máximo de espera por um bloqueio do arquivo
+    tempo_limite_bloqueio: Duration,
+
+    /// Razão de lixo que dispara a compactação automática (0 = desativada)
+    limite_compactacao: f64,
+
+    /// Se o email deve ser único entre as pessoas
+    email_unico: bool,
+
+    /// Se o arquivo já foi visto no esquema atual (nenhuma escrita o
+    /// faz voltar a um esquema antigo, então basta conferir uma vez)
+    esquema_conferido: bool,
+
+    /// Se linhas inválidas interrompem a leitura em vez de serem ignoradas
+    estrito: bool,
+
+    /// Quem faz as alterações, gravado no histórico
+    ator: Option<String>,
+}
+
+impl ArquivoBackend {
+    /// Abre (ou cria) o arquivo de dados no formato indicado
+    pub fn new(
+        caminho: &str,
+        formato: Box<dyn Formato>,
+        tempo_limite_bloqueio: Duration,
+    ) -> PhpResult<Self> {
+        let backend = Self {
+            caminho: PathBuf::from(caminho),
+            formato: Rc::from(formato),
+            tempo_limite_bloqueio,
+            limite_compactacao: LIMITE_COMPACTACAO_PADRAO,
+            email_unico: false,
+            esquema_conferido: false,
+            estrito: false,
+            ator: None,
+        };
+
+        // Inicializa o arquivo se não existir
+        backend.inicializar_arquivo()?;
+        // Recusa arquivos com cabeçalho inválido ou esquema desconhecido
+        {
+            let _bloqueio = backend.bloquear(ModoBloqueio::Compartilhado)?;
+            backend.ler_esquema()?;
+        }
+
+        Ok(backend)
+    }
+
+    /// Inicializa o arquivo com o cabeçalho do formato se não existir
+    fn inicializar_arquivo(&self) -> PhpResult {
+        // Cria diretório se não existir
+        if let Some(parent) = self.caminho.parent() {
+            if !parent.exists() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| ArmazenamentoException::io(
+                        parent, "Erro ao criar diretório", &e
+                    ))?;
+            }
+        }
+
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+
+        if !self.caminho.exists() {
+            // Cria arquivo com cabeçalho
+            let mut file = File::create(&self.caminho)
+                .map_err(|e| ArmazenamentoException::io(
+                    &self.caminho, "Erro ao criar arquivo", &e
+                ))?;
+
+            file.write_all(self.formato.cabecalho().as_bytes())
+                .map_err(|e| ArmazenamentoException::io(
+                    &self.caminho, "Erro ao escrever cabeçalho", &e
+                ))?;
+        }
+        Ok(())
+    }
+
+    /// Confere o esquema do arquivo (o chamador deve manter o bloqueio)
+    fn ler_esquema(&self) -> PhpResult<Esquema> {
+        let file = File::open(&self.caminho)
+            .map_err(|e| ArmazenamentoException::io(&self.caminho, "Erro ao abrir arquivo", &e))?;
+
+        self.esquema_de(Box::new(BufReader::new(file)))
+    }
+
+    /// Confere o esquema no início de um leitor
+    fn esquema_de(&self, leitor: Box<dyn BufRead>) -> PhpResult<Esquema> {
+        self.formato.esquema(leitor)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::InvalidData => ArmazenamentoException::dados_invalidos(
+                    &self.caminho,
+                    format!("Esquema inválido em {}: {}", self.caminho.display(), e)
+                ),
+                _ => ArmazenamentoException::io(&self.caminho, "Erro ao ler cabeçalho", &e),
+            })
+    }
+
+    /// Migra o arquivo para o esquema atual, se preciso, antes de uma
+    /// escrita incremental (o chamador deve manter o bloqueio exclusivo)
+    fn garantir_esquema_atual(&mut self) -> PhpResult {
+        if self.esquema_conferido {
+            return Ok(());
+        }
+
+        let esquema = self.ler_esquema()?;
+        if !esquema.atual {
+            if !esquema.colunas_extras.is_empty() {
+                return Err(ArmazenamentoException::dados_invalidos(&self.caminho, format!(
+                    "O arquivo {} tem colunas fora do esquema ({}); use migrarEsquema() \
+                     para convertê-lo (essas colunas serão descartadas)",
+                    self.caminho.display(),
+                    esquema.colunas_extras.join(", ")
+                )));
+            }
+            let leitura = self.ler_log()?;
+            self.reescrever_arquivo(&leitura.pessoas, leitura.maior_id)?;
+        }
+
+        self.esquema_conferido = true;
+        Ok(())
+    }
+
+    /// Lê todas as pessoas vigentes do arquivo (o chamador deve manter o bloqueio)
+    fn ler_pessoas(&self) -> PhpResult<Vec<Pessoa>> {
+        Ok(self.ler_log()?.pessoas)
+    }
+
+    /// Entradas do arquivo com as linhas inválidas já resolvidas conforme
+    /// o modo: no estrito a primeira vira erro (com caminho, número e
+    /// conteúdo da linha); no tolerante é aproveitada ou ignorada
+    fn entradas(&self, leitor: Box<dyn BufRead>) -> FluxoEntradas {
+        ler_entradas(self.formato.as_ref(), &self.caminho, self.estrito, leitor)
+    }
+
+    /// Lê o log inteiro e resolve a versão mais recente de cada pessoa
+    fn ler_log(&self) -> PhpResult<Leitura> {
+        let file = File::open(&self.caminho)
+            .map_err(|e| ArmazenamentoException::io(&self.caminho, "Erro ao abrir arquivo", &e))?;
+
+        let mut leitura = Leitura::default();
+        let mut versoes: Vec<Option<Pessoa>> = Vec::new();
+        let mut posicoes: HashMap<i64, usize> = HashMap::new();
+
+        for entrada in self.entradas(Box::new(BufReader::new(file))) {
+            let entrada = entrada?;
+            leitura.entradas += 1;
+
+            match entrada {
+                Entrada::Registro(pessoa) => {
+                    let Some(id) = pessoa.id else {
+                        versoes.push(Some(pessoa));
+                        continue;
+                    };
+                    leitura.maior_id = leitura.maior_id.max(id);
+
+                    // Nova versão ocupa a posição da original
+                    match posicoes.get(&id) {
+                        Some(&posicao) => versoes[posicao] = Some(pessoa),
+                        None => {
+                            posicoes.insert(id, versoes.len());
+                            versoes.push(Some(pessoa));
+                        }
+                    }
+                }
+                Entrada::Remocao(id) => {
+                    leitura.maior_id = leitura.maior_id.max(id);
+                    if let Some(posicao) = posicoes.remove(&id) {
+                        versoes[posicao] = None;
+                    }
+                }
+                // Já resolvidas por `entradas`
+                Entrada::Invalida(_) => {}
+            }
+        }
+
+        leitura.pessoas = versoes.into_iter().flatten().collect();
+        Ok(leitura)
+    }
+
+    /// Primeira passada da iteração: descobre o tamanho do preâmbulo e em
+    /// que deslocamento do arquivo está a versão mais recente de cada
+    /// pessoa vigente (o conteúdo é lido na segunda passada)
+    fn indexar_log(&self, file: &File, tamanho: u64) -> PhpResult<IndiceLog> {
+        let erro_leitura = |e: io::Error| ArmazenamentoException::io(
+            &self.caminho, "Erro ao ler arquivo", &e
+        );
+        let lido = Rc::new(Cell::new(0));
+        let contado = |lido: &Rc<Cell<u64>>| -> PhpResult<Box<dyn BufRead>> {
+            let leitor = BufReader::new(trecho(file, 0, tamanho).map_err(erro_leitura)?);
+            Ok(Box::new(LeitorContado { leitor, lido: Rc::clone(lido) }))
+        };
+
+        // O preâmbulo é o que `esquema` consome antes do primeiro registro
+        self.esquema_de(contado(&lido)?)?;
+        let mut cabecalho = vec![0; lido.get() as usize];
+        trecho(file, 0, tamanho)
+            .and_then(|mut leitor| leitor.read_exact(&mut cabecalho))
+            .map_err(erro_leitura)?;
+
+        // Mesma resolução de `ler_log`: a posição de criação e o
+        // deslocamento da última versão de cada ID
+        let mut vigentes: HashMap<i64, (usize, u64)> = HashMap::new();
+        let mut sem_id: Vec<(usize, u64)> = Vec::new();
+        let mut inicio = cabecalho.len() as u64;
+        lido.set(0);
+
+        for (posicao, entrada) in self.entradas(contado(&lido)?).enumerate() {
+            match entrada? {
+                Entrada::Registro(pessoa) => match pessoa.id {
+                    Some(id) => vigentes.entry(id).or_insert((posicao, inicio)).1 = inicio,
+                    None => sem_id.push((posicao, inicio)),
+                },
+                Entrada::Remocao(id) => {
+                    vigentes.remove(&id);
+                }
+                Entrada::Invalida(_) => {}
+            }
+            inicio = lido.get();
+        }
+
+        let mut ordem: Vec<(usize, u64)> = vigentes.into_values().chain(sem_id).collect();
+        ordem.sort_unstable();
+
+        Ok(IndiceLog {
+            cabecalho,
+            deslocamentos: ordem.into_iter().map(|(_, deslocamento)| deslocamento).collect(),
+        })
+    }
+
+    /// Acrescenta conteúdo ao fim do arquivo e o leva ao disco antes de
+    /// retornar (o chamador deve manter o bloqueio)
+    fn anexar(&self, conteudo: &str) -> PhpResult {
+        let mut file = self.abrir_para_anexar()?;
+
+        file.write_all(conteudo.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| ArmazenamentoException::io(
+                &self.caminho, "Erro ao escrever no arquivo", &e
+            ))
+    }
+
+    /// Acrescenta várias pessoas com um único handle bufferizado
+    /// (o chamador deve manter o bloqueio exclusivo)
+    /// Em caso de erro o arquivo volta ao tamanho original
+    fn anexar_lote(&self, pessoas: &[Pessoa]) -> PhpResult {
+        let file = self.abrir_para_anexar()?;
+
+        let erro_escrita = |e: io::Error| ArmazenamentoException::io(
+            &self.caminho, "Erro ao escrever no arquivo", &e
+        );
+        let tamanho_original = file.metadata().map_err(erro_escrita)?.len();
+
+        let resultado = {
+            let mut escritor = BufWriter::new(&file);
+            pessoas
+                .iter()
+                .try_for_each(|p| escritor.write_all(self.formato.codificar(p).as_bytes()))
+                .and_then(|_| escritor.flush())
+        }
+        .and_then(|_| file.sync_data());
+
+        if let Err(e) = resultado {
+            let _ = file.set_len(tamanho_original);
+            return Err(erro_escrita(e));
+        }
+        Ok(())
+    }
+
+    /// Abre o arquivo de dados para acréscimo, descartando antes um registro
+    /// cortado no fim (o chamador deve manter o bloqueio exclusivo)
+    fn abrir_para_anexar(&self) -> PhpResult<File> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.caminho)
+            .map_err(|e| ArmazenamentoException::io(&self.caminho, "Erro ao abrir arquivo", &e))?;
+
+        descartar_registro_cortado(&mut file, &self.formato.cabecalho())
+            .map_err(|e| ArmazenamentoException::io(
+                &self.caminho, "Erro ao reparar o fim do arquivo", &e
+            ))?;
+        Ok(file)
+    }
+
+    /// Compacta o arquivo se a razão de lixo atingiu o limite configurado
+    /// (o chamador deve manter o bloqueio exclusivo)
+    fn compactar_se_necessario(&self, leitura: &Leitura) -> PhpResult {
+        if self.limite_compactacao > 0.0 && leitura.razao_lixo() >= self.limite_compactacao {
+            self.reescrever_arquivo(&leitura.pessoas, leitura.maior_id)?;
+        }
+        Ok(())
+    }
+
+    /// Bloqueia o arquivo de dados respeitando o tempo limite configurado
+    fn bloquear(&self, modo: ModoBloqueio) -> PhpResult<Bloqueio> {
+        Bloqueio::adquirir(&self.caminho, modo, self.tempo_limite_bloqueio)
+            .map_err(|e| match e {
+                ErroBloqueio::TempoEsgotado(limite) => BloqueioException::erro(
+                    format!(
+                        "Tempo limite de {} ms esgotado ao bloquear o arquivo {}",
+                        limite.as_millis(),
+                        self.caminho.display()
+                    )
+                ),
+                ErroBloqueio::Io(e) => ArmazenamentoException::io(
+                    &self.caminho, "Erro ao bloquear arquivo", &e
+                ),
+            })
+    }
+
+    /// Próximo ID livre, a partir da leitura do arquivo feita sob o mesmo
+    /// bloqueio exclusivo que o chamador mantém até gravar o novo registro:
+    /// como o registro leva o ID ao arquivo, várias instâncias e processos
+    /// nunca entregam o mesmo ID sem que a sequência precise ser gravada
+    fn proximo_id(&self, leitura: &Leitura) -> PhpResult<i64> {
+        Ok(self.ler_sequencia()?.max(leitura.maior_id) + 1)
+    }
+
+    /// Avança a sequência persistida até `id`, se ela ainda estiver abaixo
+    fn avancar_sequencia(&self, id: i64) -> PhpResult {
+        if id > self.ler_sequencia()? {
+            self.gravar_sequencia(id)?;
+        }
+        Ok(())
+    }
+
+    /// Caminho do arquivo auxiliar com a sequência de IDs
+    fn caminho_sequencia(&self) -> PathBuf {
+        let mut nome = self.caminho.as_os_str().to_owned();
+        nome.push(".seq");
+        PathBuf::from(nome)
+    }
+
+    /// Caminho do arquivo auxiliar com o histórico de alterações
+    fn caminho_historico(&self) -> PathBuf {
+        let mut nome = self.caminho.as_os_str().to_owned();
+        nome.push(".historico");
+        PathBuf::from(nome)
+    }
+
+    /// Lê o maior ID emitido que o arquivo de dados pode não mostrar
+    /// (0 se a sequência ainda não existir)
+    fn ler_sequencia(&self) -> PhpResult<i64> {
+        match std::fs::read_to_string(self.caminho_sequencia()) {
+            Ok(conteudo) => conteudo.trim().parse::<i64>()
+                .map_err(|_| ArmazenamentoException::dados_invalidos(
+                    &self.caminho_sequencia(),
+                    format!("Sequência de IDs corrompida: {}", self.caminho_sequencia().display())
+                )),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(ArmazenamentoException::io(
+                &self.caminho_sequencia(), "Erro ao ler sequência de IDs", &e
+            )),
+        }
+    }
+
+    /// Grava o último ID emitido no arquivo de sequência
+    fn gravar_sequencia(&self, id: i64) -> PhpResult {
+        atomico::reescrever(&self.caminho_sequencia(), |file| writeln!(file, "{}", id))
+            .map_err(|e| ArmazenamentoException::io(
+                &self.caminho_sequencia(), "Erro ao gravar sequência de IDs", &e
+            ))
+    }
+
+    /// Acrescenta alterações ao histórico e as leva ao disco; retorna o
+    /// tamanho que ele tinha antes (o chamador deve manter o bloqueio exclusivo)
+    fn anexar_historico(&self, alteracoes: &[Alteracao]) -> PhpResult<u64> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(self.caminho_historico())
+            .map_err(|e| ArmazenamentoException::io(
+                &self.caminho_historico(), "Erro ao abrir histórico", &e
+            ))?;
+
+        let erro_escrita = |e: io::Error| ArmazenamentoException::io(
+            &self.caminho_historico(), "Erro ao escrever no histórico", &e
+        );
+        descartar_registro_cortado(&mut file, "").map_err(erro_escrita)?;
+        let tamanho_original = file.metadata().map_err(erro_escrita)?.len();
+
+        let resultado = {
+            let mut escritor = BufWriter::new(&file);
+            alteracoes
+                .iter()
+                .try_for_each(|a| {
+                    serde_json::to_writer(&mut escritor, a)?;
+                    escritor.write_all(b"\n")
+                })
+                .and_then(|_| escritor.flush())
+        }
+        .and_then(|_| file.sync_data());
+
+        // Nunca deixa uma alteração pela metade no fim do histórico
+        if let Err(e) = resultado {
+            let _ = file.set_len(tamanho_original);
+            return Err(erro_escrita(e));
+        }
+        Ok(tamanho_original)
+    }
+
+    /// Grava as alterações no histórico e em seguida os dados, com `gravar`,
+    /// tudo sob o bloqueio exclusivo que o chamador mantém: o histórico fica
+    /// na mesma ordem das gravações, e se os dados não forem gravados ele
+    /// volta ao tamanho anterior
+    fn gravar_com_historico<F>(&self, alteracoes: &[Alteracao], gravar: F) -> PhpResult
+    where
+        F: FnOnce() -> PhpResult,
+    {
+        if alteracoes.is_empty() {
+            return gravar();
+        }
+
+        let tamanho_original = self.anexar_historico(alteracoes)?;
+        let resultado = gravar();
+
+        if resultado.is_err() {
+            let _ = OpenOptions::new()
+                .write(true)
+                .open(self.caminho_historico())
+                .and_then(|file| file.set_len(tamanho_original));
+        }
+        resultado
+    }
+
+    /// Reescreve o arquivo completamente com nova lista
+    /// A troca é atômica: o arquivo original só é substituído depois que
+    /// a nova versão estiver inteira e sincronizada em disco
+    /// `maior_id` é o maior ID que o arquivo substituído já teve; se ele não
+    /// estiver na nova lista, fica na sequência para nunca ser reaproveitado
+    fn reescrever_arquivo(&self, pessoas: &[Pessoa], maior_id: i64) -> PhpResult {
+        let maior_mantido = pessoas.iter().filter_map(|p| p.id).max().unwrap_or(0);
+        if maior_id > maior_mantido {
+            self.avancar_sequencia(maior_id)?;
+        }
+
+        atomico::reescrever(&self.caminho, |file| {
+            // Escreve cabeçalho
+            file.write_all(self.formato.cabecalho().as_bytes())?;
+
+            // Escreve cada pessoa
+            for pessoa in pessoas {
+                file.write_all(self.formato.codificar(pessoa).as_bytes())?;
+            }
+
+            Ok(())
+        })
+        .map_err(|e| ArmazenamentoException::io(&self.caminho, "Erro ao reescrever arquivo", &e))
+    }
+}
+
+impl StorageBackend for ArquivoBackend {
+    fn criar(&mut self, pessoa: &mut Pessoa) -> PhpResult<i64> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+        self.garantir_esquema_atual()?;
+        let leitura = self.ler_log()?;
+
+        if self.email_unico {
+            verificar_emails_unicos(&leitura.pessoas, slice::from_ref(pessoa))?;
+        }
+
+        // Atribui novo ID (alocado sob o bloqueio exclusivo)
+        let novo_id = self.proximo_id(&leitura)?;
+        pessoa.definir_id(novo_id);
+
+        let alteracoes: Vec<Alteracao> =
+            Alteracao::new(None, Some(pessoa.clone()), self.ator.clone()).into_iter().collect();
+        self.gravar_com_historico(&alteracoes, || self.anexar(&self.formato.codificar(pessoa)))?;
+        Ok(novo_id)
+    }
+
+    fn criar_varios(&mut self, pessoas: &mut [Pessoa]) -> PhpResult<Vec<i64>> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+        self.garantir_esquema_atual()?;
+        let leitura = self.ler_log()?;
+
+        if self.email_unico {
+            verificar_emails_unicos(&leitura.pessoas, pessoas)?;
+        }
+
+        // Todos entram no mesmo acréscimo: a faixa de IDs é contínua
+        let primeiro = self.proximo_id(&leitura)?;
+        let ids: Vec<i64> = (primeiro..).take(pessoas.len()).collect();
+
+        for (pessoa, id) in pessoas.iter_mut().zip(&ids) {
+            pessoa.definir_id(*id);
+        }
+
+        let alteracoes: Vec<Alteracao> = pessoas
+            .iter()
+            .filter_map(|p| Alteracao::new(None, Some(p.clone()), self.ator.clone()))
+            .collect();
+        self.gravar_com_historico(&alteracoes, || self.anexar_lote(pessoas))?;
+        Ok(ids)
+    }
+
+    fn atualizar(&mut self, pessoa: &Pessoa) -> PhpResult<bool> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+        self.garantir_esquema_atual()?;
+        let mut leitura = self.ler_log()?;
+
+        if self.email_unico {
+            verificar_emails_unicos(&leitura.pessoas, slice::from_ref(pessoa))?;
+        }
+
+        // Busca a pessoa
+        let anterior = match leitura.pessoas.iter_mut().find(|p| p.id == pessoa.id) {
+            Some(p) => {
+                verificar_versao(p, pessoa)?;
+                mem::replace(p, pessoa.clone())
+            }
+            None => return Ok(false),
+        };
+
+        // Acrescenta a nova versão ao fim do arquivo
+        let alteracoes: Vec<Alteracao> =
+            Alteracao::new(Some(anterior), Some(pessoa.clone()), self.ator.clone()).into_iter().collect();
+        self.gravar_com_historico(&alteracoes, || self.anexar(&self.formato.codificar(pessoa)))?;
+        leitura.entradas += 1;
+
+        self.compactar_se_necessario(&leitura)?;
+        Ok(true)
+    }
+
+    fn deletar(&mut self, id: i64) -> PhpResult<bool> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+        self.garantir_esquema_atual()?;
+        let mut leitura = self.ler_log()?;
+
+        // Remove a pessoa da lista
+        let Some(posicao) = leitura.pessoas.iter().position(|p| p.id == Some(id)) else {
+            return Ok(false);
+        };
+        let anterior = leitura.pessoas.remove(posicao);
+
+        // Acrescenta a marca de remoção ao fim do arquivo
+        let alteracoes: Vec<Alteracao> =
+            Alteracao::new(Some(anterior), None, self.ator.clone()).into_iter().collect();
+        self.gravar_com_historico(&alteracoes, || self.anexar(&self.formato.codificar_remocao(id)))?;
+        leitura.entradas += 1;
+
+        self.compactar_se_necessario(&leitura)?;
+        Ok(true)
+    }
+
+    fn listar(&self) -> PhpResult<Vec<Pessoa>> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Compartilhado)?;
+        self.ler_pessoas()
+    }
+
+    fn substituir_todos(&mut self, mut pessoas: Vec<Pessoa>) -> PhpResult {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+
+        // Registros sem ID recebem um novo, acima de todos os já emitidos
+        // e dos recebidos (IDs do arquivo substituído nunca são reaproveitados)
+        let anterior = self.ler_log()?;
+        let maior_recebido = pessoas.iter().filter_map(|p| p.id).max().unwrap_or(0);
+        let proximo = self.proximo_id(&anterior)?.max(maior_recebido + 1);
+        for (pessoa, id) in pessoas.iter_mut().filter(|p| p.id.is_none()).zip(proximo..) {
+            pessoa.id = Some(id);
+        }
+
+        self.reescrever_arquivo(&pessoas, anterior.maior_id)
+    }
+
+    fn iterar(&self) -> PhpResult<FluxoPessoas> {
+        // Fotografia do arquivo: o handle continua apontando para a mesma
+        // versão mesmo que ele seja reescrito (rename), e acréscimos feitos
+        // depois ficam além do tamanho registrado aqui
+        let (file, tamanho) = {
+            let _bloqueio = self.bloquear(ModoBloqueio::Compartilhado)?;
+            let file = File::open(&self.caminho)
+                .map_err(|e| ArmazenamentoException::io(
+                    &self.caminho, "Erro ao abrir arquivo", &e
+                ))?;
+            let tamanho = file.metadata()
+                .map_err(|e| ArmazenamentoException::io(&self.caminho, "Erro ao ler arquivo", &e))?
+                .len();
+            (file, tamanho)
+        };
+
+        let indice = self.indexar_log(&file, tamanho)?;
+
+        Ok(Box::new(IteradorLog {
+            file,
+            tamanho,
+            cabecalho: indice.cabecalho,
+            formato: Rc::clone(&self.formato),
+            caminho: self.caminho.clone(),
+            estrito: self.estrito,
+            deslocamentos: indice.deslocamentos.into_iter(),
+            entradas: None,
+            lido: Rc::default(),
+        }))
+    }
+
+    fn reservar_ids(&mut self, quantidade: i64) -> PhpResult<i64> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+        let primeiro = self.proximo_id(&self.ler_log()?)?;
+
+        // Os IDs reservados só chegam ao arquivo depois: a sequência os
+        // guarda até lá (uma queda antes disso deixa no máximo um buraco)
+        self.avancar_sequencia(primeiro + quantidade - 1)?;
+        Ok(primeiro)
+    }
+
+    fn aplicar(&mut self, operacoes: Vec<Operacao>) -> PhpResult {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+        self.garantir_esquema_atual()?;
+        let leitura = self.ler_log()?;
+        let mut pessoas = leitura.pessoas;
+        let alteracoes = alteracoes_das_operacoes(&pessoas, &operacoes, &self.ator);
+
+        // Tudo é resolvido em memória antes de tocar no arquivo: se alguma
+        // operação falhar, o arquivo continua exatamente como estava
+        aplicar_operacoes(&mut pessoas, &operacoes)?;
+        if self.email_unico {
+            verificar_operacoes(&pessoas, &operacoes)?;
+        }
+        self.gravar_com_historico(&alteracoes, || self.reescrever_arquivo(&pessoas, leitura.maior_id))
+    }
+
+    fn compactar(&mut self) -> PhpResult<i64> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+        self.garantir_esquema_atual()?;
+        let leitura = self.ler_log()?;
+
+        self.reescrever_arquivo(&leitura.pessoas, leitura.maior_id)?;
+        Ok((leitura.entradas - leitura.pessoas.len()) as i64)
+    }
+
+    fn caminho(&self) -> String {
+        self.caminho.to_string_lossy().into_owned()
+    }
+
+    fn versao_esquema(&self) -> PhpResult<i64> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Compartilhado)?;
+        Ok(self.ler_esquema()?.versao)
+    }
+
+    fn migrar_esquema(&mut self) -> PhpResult<bool> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Exclusivo)?;
+
+        if self.ler_esquema()?.atual {
+            self.esquema_conferido = true;
+            return Ok(false);
+        }
+
+        // A releitura pelo cabeçalho antigo e a gravação no atual convertem
+        // tudo de uma vez; colunas fora do esquema ficam para trás
+        let leitura = self.ler_log()?;
+        self.reescrever_arquivo(&leitura.pessoas, leitura.maior_id)?;
+        self.esquema_conferido = true;
+        Ok(true)
+    }
+
+    fn verificar(&self) -> PhpResult<Verificacao> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Compartilhado)?;
+        let file = File::open(&self.caminho)
+            .map_err(|e| ArmazenamentoException::io(&self.caminho, "Erro ao abrir arquivo", &e))?;
+
+        let mut verificacao = Verificacao::default();
+        let mut versoes: Vec<Option<Pessoa>> = Vec::new();
+        let mut posicoes: HashMap<i64, usize> = HashMap::new();
+        let mut removidos: HashSet<i64> = HashSet::new();
+        let mut duplicados: HashSet<i64> = HashSet::new();
+
+        for entrada in self.formato.ler(Box::new(BufReader::new(file))) {
+            let pessoa = match entrada {
+                Ok(Entrada::Registro(pessoa)) => pessoa,
+                Ok(Entrada::Remocao(id)) => {
+                    if let Some(posicao) = posicoes.remove(&id) {
+                        versoes[posicao] = None;
+                    }
+                    removidos.insert(id);
+                    continue;
+                }
+                Ok(Entrada::Invalida(invalida)) => {
+                    let aproveitavel = invalida.aproveitavel.clone();
+                    verificacao.linhas_invalidas.push(invalida);
+                    match aproveitavel {
+                        Some(pessoa) => pessoa,
+                        None => continue,
+                    }
+                }
+                // O restante do arquivo não pode ser lido
+                Err(e) => {
+                    verificacao.linhas_invalidas.push(LinhaInvalida {
+                        linha: 0,
+                        conteudo: String::new(),
+                        motivo: e.to_string(),
+                        aproveitavel: None,
+                    });
+                    break;
+                }
+            };
+
+            let Some(id) = pessoa.id else {
+                versoes.push(Some(pessoa));
+                continue;
+            };
+
+            // IDs nunca são reaproveitados, e cada atualização grava uma
+            // versão maior: fora disso são dois registros com o mesmo ID
+            let repetido = match posicoes.get(&id) {
+                Some(&posicao) => versoes[posicao]
+                    .as_ref()
+                    .is_some_and(|anterior| pessoa.versao > 0 && pessoa.versao <= anterior.versao),
+                None => removidos.contains(&id),
+            };
+            if repetido && duplicados.insert(id) {
+                verificacao.ids_duplicados.push(id);
+            }
+
+            match posicoes.get(&id) {
+                Some(&posicao) => versoes[posicao] = Some(pessoa),
+                None => {
+                    posicoes.insert(id, versoes.len());
+                    versoes.push(Some(pessoa));
+                }
+            }
+        }
+
+        verificacao.pessoas = versoes.into_iter().flatten().collect();
+        Ok(verificacao)
+    }
+
+    fn historico(&self, id: i64) -> PhpResult<Vec<Alteracao>> {
+        let _bloqueio = self.bloquear(ModoBloqueio::Compartilhado)?;
+
+        let file = match File::open(self.caminho_historico()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ArmazenamentoException::io(
+                &self.caminho_historico(), "Erro ao abrir histórico", &e
+            )),
+        };
+
+        let mut alteracoes = Vec::new();
+        for (i, linha) in BufReader::new(file).lines().enumerate() {
+            let linha = linha.map_err(|e| ArmazenamentoException::io(
+                &self.caminho_historico(), "Erro ao ler histórico", &e
+            ))?;
+            if linha.trim().is_empty() {
+                continue;
+            }
+
+            let alteracao: Alteracao = serde_json::from_str(&linha)
+                .map_err(|e| ArmazenamentoException::dados_invalidos(
+                    &self.caminho_historico(),
+                    format!("Histórico inválido na linha {}: {}", i + 1, e)
+                ))?;
+            if alteracao.id_pessoa == id {
+                alteracoes.push(alteracao);
+            }
+        }
+
+        Ok(alteracoes)
+    }
+
+    fn definir_email_unico(&mut self, ativo: bool) {
+        self.email_unico = ativo;
+    }
+
+    fn definir_ator(&mut self, ator: Option<String>) {
+        self.ator = ator;
+    }
+
+    fn definir_modo_estrito(&mut self, ativo: bool) {
+        self.estrito = ativo;
+    }
+
+    fn definir_tempo_limite_bloqueio(&mut self, tempo_limite: Duration) {
+        self.tempo_limite_bloqueio = tempo_limite;
+    }
+
+    fn definir_limite_compactacao(&mut self, razao: f64) {
+        self.limite_compactacao = razao;
+    }
+}
+
+/// Entradas lidas por `formato` com as linhas inválidas resolvidas conforme
+/// o modo (ver `ArquivoBackend::entradas`)
+fn ler_entradas(
+    formato: &dyn Formato,
+    caminho: &Path,
+    estrito: bool,
+    leitor: Box<dyn BufRead>,
+) -> FluxoEntradas {
+    let caminho = caminho.to_path_buf();
+
+    Box::new(formato.ler(leitor).filter_map(move |entrada| match entrada {
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Some(Err(
+            ArmazenamentoException::dados_invalidos(&caminho, format!("Erro ao ler linha: {}", e))
+        )),
+        Err(e) => Some(Err(ArmazenamentoException::io(&caminho, "Erro ao ler linha", &e))),
+        Ok(Entrada::Invalida(invalida)) if estrito => Some(Err(ArmazenamentoException::dados_invalidos(
+            &caminho,
+            format!(
+                "Registro inválido em {}, linha {}: {} (conteúdo: {})",
+                caminho.display(),
+                invalida.linha,
+                invalida.motivo,
+                invalida.conteudo
+            )
+        ))),
+        Ok(Entrada::Invalida(invalida)) => invalida.aproveitavel.map(|p| Ok(Entrada::Registro(p))),
+        Ok(entrada) => Some(Ok(entrada)),
+    }))
+}
+
+/// Trecho `[inicio, tamanho)` de um arquivo, por um handle próprio
+fn trecho(file: &File, inicio: u64, tamanho: u64) -> io::Result<io::Take<File>> {
+    let mut copia = file.try_clone()?;
+    copia.seek(SeekFrom::Start(inicio))?;
+    Ok(copia.take(tamanho.saturating_sub(inicio)))
+}
+
+/// Leitor que conta os bytes consumidos, para saber onde cada registro
+/// começa (os formatos consomem exatamente até o fim de cada registro)
+struct LeitorContado<R> {
+    leitor: R,
+    lido: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for LeitorContado<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let lidos = self.leitor.read(buf)?;
+        self.lido.set(self.lido.get() + lidos as u64);
+        Ok(lidos)
+    }
+}
+
+impl<R: BufRead> BufRead for LeitorContado<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.leitor.fill_buf()
+    }
+
+    fn consume(&mut self, quantidade: usize) {
+        self.leitor.consume(quantidade);
+        self.lido.set(self.lido.get() + quantidade as u64);
+    }
+}
+
+/// Segunda passada de `iterar`: lê cada pessoa no deslocamento guardado
+/// pelo índice, em sequência enquanto os registros estão em ordem
+struct IteradorLog {
+    /// Handle da fotografia do arquivo tirada por `iterar`
+    file: File,
+    tamanho: u64,
+    cabecalho: Vec<u8>,
+    formato: Rc<dyn Formato>,
+    caminho: PathBuf,
+    estrito: bool,
+    deslocamentos: std::vec::IntoIter<u64>,
+
+    /// Entradas sendo lidas (None antes da primeira)
+    entradas: Option<FluxoEntradas>,
+
+    /// Posição (no arquivo) até onde `entradas` foi consumida
+    lido: Rc<Cell<u64>>,
+}
+
+impl IteradorLog {
+    /// Passa a ler a partir de `deslocamento`, com o preâmbulo à frente
+    /// para o formato interpretar os registros
+    fn reposicionar(&mut self, deslocamento: u64) -> PhpResult {
+        let registros = trecho(&self.file, deslocamento, self.tamanho)
+            .map_err(|e| ArmazenamentoException::io(&self.caminho, "Erro ao ler arquivo", &e))?;
+        self.lido = Rc::new(Cell::new(deslocamento - self.cabecalho.len() as u64));
+        let leitor = LeitorContado {
+            leitor: BufReader::new(io::Cursor::new(self.cabecalho.clone()).chain(registros)),
+            lido: Rc::clone(&self.lido),
+        };
+
+        self.entradas = Some(ler_entradas(self.formato.as_ref(), &self.caminho, self.estrito, Box::new(leitor)));
+        Ok(())
+    }
+}
+
+impl Iterator for IteradorLog {
+    type Item = PhpResult<Pessoa>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let deslocamento = self.deslocamentos.next()?;
+
+        if self.entradas.is_none() || self.lido.get() != deslocamento {
+            if let Err(e) = self.reposicionar(deslocamento) {
+                return Some(Err(e));
+            }
+        }
+
+        self.entradas.as_mut()?.find_map(|entrada| match entrada {
+            Ok(Entrada::Registro(pessoa)) => Some(Ok(pessoa)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+}
+
+/// Descarta um registro cortado no fim do arquivo (sem a quebra de linha
+/// final), voltando ao fim da última linha completa; sem nenhuma linha
+/// completa o arquivo volta a ter só `cabecalho`
+fn descartar_registro_cortado(file: &mut File, cabecalho: &str) -> io::Result<()> {
+    const BLOCO: u64 = 4096;
+
+    let mut fim = file.metadata()?.len();
+    let mut ultimo = [0u8; 1];
+    if fim == 0 {
+        return Ok(());
+    }
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut ultimo)?;
+    if ultimo[0] == b'\n' {
+        return Ok(());
+    }
+
+    // Procura a última quebra de linha, de trás para frente
+    let mut bloco = vec![0u8; BLOCO as usize];
+    while fim > 0 {
+        let inicio = fim.saturating_sub(BLOCO);
+        let trecho = &mut bloco[..(fim - inicio) as usize];
+        file.seek(SeekFrom::Start(inicio))?;
+        file.read_exact(trecho)?;
+
+        if let Some(posicao) = trecho.iter().rposition(|&b| b == b'\n') {
+            file.set_len(inicio + posicao as u64 + 1)?;
+            return file.sync_data();
+        }
+        fim = inicio;
+    }
+
+    file.set_len(0)?;
+    file.write_all(cabecalho.as_bytes())?;
+    file.sync_data()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wead::csv::FormatoCsv;
+    use crate::wead::jsonl::FormatoJsonl;
+
+    /// Backend sobre um arquivo novo num diretório temporário exclusivo
+    fn backend(nome: &str, formato: Box<dyn Formato>) -> ArquivoBackend {
+        let diretorio = std::env::temp_dir().join(format!("wead_arquivo_{}_{}", std::process::id(), nome));
+        let _ = std::fs::remove_dir_all(&diretorio);
+        std::fs::create_dir_all(&diretorio).unwrap();
+
+        let caminho = diretorio.join(nome);
+        ArquivoBackend::new(caminho.to_str().unwrap(), formato, Duration::from_secs(1)).unwrap()
+    }
+
+    fn criar(backend: &mut ArquivoBackend, nome: &str) -> i64 {
+        let mut pessoa = Pessoa::__construct(nome.into(), format!("{}@x.com", nome), "1".into());
+        backend.criar(&mut pessoa).unwrap()
+    }
+
+    /// Segunda instância sobre o mesmo arquivo, como outro processo
+    fn reabrir(backend: &ArquivoBackend, formato: Box<dyn Formato>) -> ArquivoBackend {
+        ArquivoBackend::new(backend.caminho.to_str().unwrap(), formato, Duration::from_secs(1)).unwrap()
+    }
+
+    /// Corta os últimos `bytes` do arquivo de dados, como uma queda no meio da escrita
+    fn cortar(backend: &ArquivoBackend, bytes: u64) {
+        let file = OpenOptions::new().write(true).open(&backend.caminho).unwrap();
+        let tamanho = file.metadata().unwrap().len();
+        file.set_len(tamanho - bytes).unwrap();
+    }
+
+    fn nomes(backend: &ArquivoBackend) -> Vec<String> {
+        backend.listar().unwrap().into_iter().map(|p| p.nome).collect()
+    }
+
+    #[test]
+    fn registro_cortado_descartado_antes_de_anexar() {
+        for (nome, formato) in [
+            ("a.csv", Box::new(FormatoCsv) as Box<dyn Formato>),
+            ("a.jsonl", Box::new(FormatoJsonl)),
+        ] {
+            let mut backend = backend(nome, formato);
+            criar(&mut backend, "Ana");
+            criar(&mut backend, "Bia");
+            cortar(&backend, 5);
+
+            criar(&mut backend, "Cid");
+            assert_eq!(nomes(&backend), ["Ana", "Cid"], "{}", nome);
+            assert!(backend.verificar().unwrap().linhas_invalidas.is_empty(), "{}", nome);
+        }
+    }
+
+    #[test]
+    fn iterar_na_ordem_de_listar() {
+        for (nome, formato) in [
+            ("ordem.csv", Box::new(FormatoCsv) as Box<dyn Formato>),
+            ("ordem.jsonl", Box::new(FormatoJsonl)),
+        ] {
+            let mut backend = backend(nome, formato);
+            backend.definir_limite_compactacao(0.0);
+            for nome in ["Ana", "Bia", "Cid", "Davi"] {
+                criar(&mut backend, nome);
+            }
+
+            // Versões novas vão para o fim do log, inclusive com quebra de linha no nome
+            let mut ana = backend.buscar(1).unwrap().unwrap();
+            ana.nome = "Ana\nMaria".into();
+            ana.versao += 1;
+            backend.atualizar(&ana).unwrap();
+            backend.deletar(2).unwrap();
+            criar(&mut backend, "Eva");
+
+            let iterados: Vec<String> = backend.iterar().unwrap().map(|p| p.unwrap().nome).collect();
+            assert_eq!(iterados, nomes(&backend), "{}", nome);
+            assert_eq!(iterados, ["Ana\nMaria", "Cid", "Davi", "Eva"], "{}", nome);
+        }
+    }
+
+    #[test]
+    fn lote_depois_de_registro_cortado() {
+        let mut backend = backend("lote.csv", Box::new(FormatoCsv));
+        criar(&mut backend, "Ana");
+        cortar(&backend, 3);
+
+        let mut pessoas = vec![
+            Pessoa::__construct("Bia".into(), "bia@x.com".into(), "1".into()),
+            Pessoa::__construct("Cid".into(), "cid@x.com".into(), "1".into()),
+        ];
+        backend.criar_varios(&mut pessoas).unwrap();
+        assert_eq!(nomes(&backend), ["Bia", "Cid"]);
+    }
+
+    #[test]
+    fn criacao_comum_nao_grava_sequencia() {
+        let mut backend = backend("seq.csv", Box::new(FormatoCsv));
+        let ids: Vec<i64> = ["Ana", "Bia", "Cid"].iter().map(|n| criar(&mut backend, n)).collect();
+
+        assert_eq!(ids, [1, 2, 3]);
+        assert!(!backend.caminho_sequencia().exists());
+    }
+
+    #[test]
+    fn ids_unicos_entre_instancias() {
+        let mut a = backend("instancias.jsonl", Box::new(FormatoJsonl));
+        let mut b = reabrir(&a, Box::new(FormatoJsonl));
+
+        assert_eq!(criar(&mut a, "Ana"), 1);
+        assert_eq!(criar(&mut b, "Bia"), 2);
+        assert_eq!(criar(&mut a, "Cid"), 3);
+
+        // Reservas ainda não estão no arquivo: só a sequência as protege
+        assert_eq!(b.reservar_ids(5).unwrap(), 4);
+        assert_eq!(criar(&mut a, "Davi"), 9);
+    }
+
+    #[test]
+    fn ids_removidos_nao_reaproveitados() {
+        let mut backend = backend("removidos.csv", Box::new(FormatoCsv));
+        backend.definir_limite_compactacao(0.0);
+        criar(&mut backend, "Ana");
+        criar(&mut backend, "Bia");
+
+        // A marca de remoção mantém o ID no arquivo
+        backend.deletar(2).unwrap();
+        assert!(!backend.caminho_sequencia().exists());
+        assert_eq!(criar(&mut backend, "Cid"), 3);
+
+        // A compactação descarta o maior ID: ele passa para a sequência
+        backend.deletar(3).unwrap();
+        backend.compactar().unwrap();
+        assert_eq!(backend.ler_sequencia().unwrap(), 3);
+        assert_eq!(criar(&mut reabrir(&backend, Box::new(FormatoCsv)), "Davi"), 4);
+    }
+
+    #[test]
+    fn sequencia_existente_respeitada() {
+        let mut backend = backend("existente.csv", Box::new(FormatoCsv));
+        std::fs::write(backend.caminho_sequencia(), "41\n").unwrap();
+
+        assert_eq!(criar(&mut backend, "Ana"), 42);
+        assert_eq!(criar(&mut backend, "Bia"), 43);
+        assert_eq!(backend.ler_sequencia().unwrap(), 41);
+    }
+}
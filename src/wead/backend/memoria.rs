@@ -0,0 +1,271 @@
+use ext_php_rs::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::mem;
+use std::slice;
+
+use super::{
+    aplicar_operacoes, verificar_emails_unicos, verificar_operacoes, verificar_versao, Operacao,
+    StorageBackend,
+};
+use crate::wead::historico::{alteracoes_das_operacoes, Alteracao};
+use crate::wead::Pessoa;
+
+// ============================================================================
+// BACKEND: MemoriaBackend
+// Armazenamento apenas na memória do processo (ideal para testes)
+// ============================================================================
+
+/// Conteúdo de um armazenamento em memória
+#[derive(Debug, Default)]
+struct DadosMemoria {
+    /// Pessoas na ordem de inserção
+    pessoas: Vec<Pessoa>,
+
+    /// Último ID emitido (nunca reaproveitado, como no CSV)
+    ultimo_id: i64,
+
+    /// Histórico de alterações, na ordem em que aconteceram
+    historico: Vec<Alteracao>,
+}
+
+thread_local! {
+    /// Armazenamentos nomeados (`memory:nome`), compartilhados entre as
+    /// instâncias de Storage da mesma thread
+    /// Não são limpos ao fim da requisição: em workers que atendem várias
+    /// (PHP-FPM, servidor embutido) o conteúdo passa para as seguintes e
+    /// só some quando o worker termina
+    static NOMEADOS: RefCell<HashMap<String, Rc<RefCell<DadosMemoria>>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Backend em memória com o mesmo comportamento do backend de arquivo
+#[derive(Debug)]
+pub struct MemoriaBackend {
+    /// Nome do armazenamento (vazio = privado desta instância)
+    nome: String,
+
+    /// Dados, possivelmente compartilhados com outras instâncias
+    dados: Rc<RefCell<DadosMemoria>>,
+
+    /// Se o email deve ser único entre as pessoas
+    email_unico: bool,
+
+    /// Quem faz as alterações, gravado no histórico
+    ator: Option<String>,
+}
+
+impl MemoriaBackend {
+    /// Abre o armazenamento `nome`, ou um privado quando `nome` é vazio
+    pub fn new(nome: &str) -> Self {
+        let dados = if nome.is_empty() {
+            Rc::default()
+        } else {
+            NOMEADOS.with(|nomeados| {
+                Rc::clone(nomeados.borrow_mut().entry(nome.to_string()).or_default())
+            })
+        };
+
+        Self {
+            nome: nome.to_string(),
+            dados,
+            email_unico: false,
+            ator: None,
+        }
+    }
+
+    /// Zera todos os armazenamentos nomeados (dados, sequência de IDs e histórico)
+    pub fn resetar_todos() {
+        NOMEADOS.with(|nomeados| {
+            for dados in nomeados.borrow().values() {
+                *dados.borrow_mut() = DadosMemoria::default();
+            }
+        });
+    }
+}
+
+impl StorageBackend for MemoriaBackend {
+    fn criar(&mut self, pessoa: &mut Pessoa) -> PhpResult<i64> {
+        let mut dados = self.dados.borrow_mut();
+
+        if self.email_unico {
+            verificar_emails_unicos(&dados.pessoas, slice::from_ref(pessoa))?;
+        }
+
+        dados.ultimo_id += 1;
+
+        let novo_id = dados.ultimo_id;
+        pessoa.definir_id(novo_id);
+        dados.pessoas.push(pessoa.clone());
+        dados.historico.extend(Alteracao::new(None, Some(pessoa.clone()), self.ator.clone()));
+        Ok(novo_id)
+    }
+
+    fn atualizar(&mut self, pessoa: &Pessoa) -> PhpResult<bool> {
+        let mut dados = self.dados.borrow_mut();
+
+        if self.email_unico && dados.pessoas.iter().any(|p| p.id == pessoa.id) {
+            verificar_emails_unicos(&dados.pessoas, slice::from_ref(pessoa))?;
+        }
+
+        let anterior = match dados.pessoas.iter_mut().find(|p| p.id == pessoa.id) {
+            Some(p) => {
+                verificar_versao(p, pessoa)?;
+                mem::replace(p, pessoa.clone())
+            }
+            None => return Ok(false),
+        };
+
+        dados.historico.extend(Alteracao::new(Some(anterior), Some(pessoa.clone()), self.ator.clone()));
+        Ok(true)
+    }
+
+    fn deletar(&mut self, id: i64) -> PhpResult<bool> {
+        let mut dados = self.dados.borrow_mut();
+
+        let Some(posicao) = dados.pessoas.iter().position(|p| p.id == Some(id)) else {
+            return Ok(false);
+        };
+        let anterior = dados.pessoas.remove(posicao);
+
+        dados.historico.extend(Alteracao::new(Some(anterior), None, self.ator.clone()));
+        Ok(true)
+    }
+
+    fn listar(&self) -> PhpResult<Vec<Pessoa>> {
+        Ok(self.dados.borrow().pessoas.clone())
+    }
+
+    fn substituir_todos(&mut self, mut pessoas: Vec<Pessoa>) -> PhpResult {
+        let mut dados = self.dados.borrow_mut();
+
+        let maior_id = pessoas.iter().filter_map(|p| p.id).max().unwrap_or(0);
+        dados.ultimo_id = dados.ultimo_id.max(maior_id);
+
+        // Registros sem ID recebem um novo
+        for pessoa in pessoas.iter_mut().filter(|p| p.id.is_none()) {
+            dados.ultimo_id += 1;
+            pessoa.id = Some(dados.ultimo_id);
+        }
+
+        dados.pessoas = pessoas;
+        Ok(())
+    }
+
+    fn aplicar(&mut self, operacoes: Vec<Operacao>) -> PhpResult {
+        let mut pessoas = self.dados.borrow().pessoas.clone();
+        let alteracoes = alteracoes_das_operacoes(&pessoas, &operacoes, &self.ator);
+
+        aplicar_operacoes(&mut pessoas, &operacoes)?;
+        if self.email_unico {
+            verificar_operacoes(&pessoas, &operacoes)?;
+        }
+        self.substituir_todos(pessoas)?;
+        self.dados.borrow_mut().historico.extend(alteracoes);
+        Ok(())
+    }
+
+    fn reservar_ids(&mut self, quantidade: i64) -> PhpResult<i64> {
+        let mut dados = self.dados.borrow_mut();
+        let primeiro = dados.ultimo_id + 1;
+
+        dados.ultimo_id += quantidade;
+        Ok(primeiro)
+    }
+
+    fn historico(&self, id: i64) -> PhpResult<Vec<Alteracao>> {
+        Ok(self.dados
+            .borrow()
+            .historico
+            .iter()
+            .filter(|a| a.id_pessoa == id)
+            .cloned()
+            .collect())
+    }
+
+    fn definir_email_unico(&mut self, ativo: bool) {
+        self.email_unico = ativo;
+    }
+
+    fn definir_ator(&mut self, ator: Option<String>) {
+        self.ator = ator;
+    }
+
+    fn caminho(&self) -> String {
+        format!("memory:{}", self.nome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nova(nome: &str) -> Pessoa {
+        Pessoa::__construct(nome.into(), format!("{}@x.com", nome.to_lowercase()), "1".into())
+    }
+
+    #[test]
+    fn nomeados_compartilhados_e_privados_isolados() {
+        let mut escrita = MemoriaBackend::new("teste_compartilhado");
+        escrita.criar(&mut nova("Ana")).unwrap();
+
+        assert_eq!(MemoriaBackend::new("teste_compartilhado").listar().unwrap().len(), 1);
+        assert!(MemoriaBackend::new("outro_nome").listar().unwrap().is_empty());
+
+        let mut privado = MemoriaBackend::new("");
+        privado.criar(&mut nova("Bia")).unwrap();
+        assert!(MemoriaBackend::new("").listar().unwrap().is_empty());
+
+        MemoriaBackend::resetar_todos();
+        assert!(escrita.listar().unwrap().is_empty());
+        assert_eq!(escrita.criar(&mut nova("Cid")).unwrap(), 1);
+    }
+
+    #[test]
+    fn ids_nao_reaproveitados() {
+        let mut backend = MemoriaBackend::new("");
+        backend.criar(&mut nova("Ana")).unwrap();
+        backend.criar(&mut nova("Bia")).unwrap();
+        assert!(backend.deletar(2).unwrap());
+        assert!(!backend.deletar(2).unwrap());
+
+        assert_eq!(backend.criar(&mut nova("Cid")).unwrap(), 3);
+    }
+
+    #[test]
+    fn atualizacao_exige_a_versao_seguinte() {
+        let mut backend = MemoriaBackend::new("");
+        let mut ana = nova("Ana");
+        backend.criar(&mut ana).unwrap();
+
+        // Duas cópias lidas na mesma versão: só a primeira gravação vale
+        let mut primeira = ana.clone();
+        primeira.nome = "Ana Maria".into();
+        primeira.versao += 1;
+        let mut segunda = ana.clone();
+        segunda.nome = "Ana Paula".into();
+        segunda.versao += 1;
+
+        assert!(backend.atualizar(&primeira).unwrap());
+        assert!(backend.atualizar(&segunda).is_err());
+        assert_eq!(backend.buscar(1).unwrap().unwrap().nome, "Ana Maria");
+
+        let mut inexistente = nova("Zé");
+        inexistente.definir_id(99);
+        inexistente.versao = 1;
+        assert!(!backend.atualizar(&inexistente).unwrap());
+    }
+
+    #[test]
+    fn email_unico_ignora_caixa_e_espacos() {
+        let mut backend = MemoriaBackend::new("");
+        backend.definir_email_unico(true);
+        backend.criar(&mut nova("Ana")).unwrap();
+
+        let mut repetido = nova("Outra");
+        repetido.email = " ANA@x.com ".into();
+        assert!(backend.criar(&mut repetido).is_err());
+        assert_eq!(backend.listar().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,817 @@
+use ext_php_rs::prelude::*;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, Row, TransactionBehavior};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{
+    conflito_versao, email_duplicado, nao_encontrada, normalizar_email, verificar_versao,
+    ArquivoBackend, FluxoPessoas, Operacao, StorageBackend, VERSAO_ESQUEMA,
+};
+use crate::wead::csv::FormatoCsv;
+use crate::wead::historico::{alteracoes_das_operacoes, Alteracao};
+use crate::wead::{ArmazenamentoException, BloqueioException, Pessoa, WeadException};
+
+// ============================================================================
+// BACKEND: SqliteBackend
+// Armazenamento em SQLite embarcado (feature "sqlite")
+// ============================================================================
+
+/// Estrutura da tabela e índices no esquema atual; executada só quando o
+/// banco é novo ou está em um esquema anterior (ver `atualizar_esquema`)
+const ESQUEMA: &str = "
+    CREATE TABLE IF NOT EXISTS pessoas (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        nome        TEXT NOT NULL,
+        email       TEXT NOT NULL,
+        telefone    TEXT NOT NULL,
+        deletado_em INTEGER,
+        versao      INTEGER NOT NULL DEFAULT 0,
+        documento   TEXT
+    );
+    CREATE TABLE IF NOT EXISTS historico (
+        seq       INTEGER PRIMARY KEY AUTOINCREMENT,
+        id_pessoa INTEGER NOT NULL,
+        operacao  TEXT NOT NULL,
+        anterior  TEXT,
+        nova      TEXT,
+        momento   INTEGER NOT NULL,
+        ator      TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_historico_pessoa ON historico (id_pessoa, seq);
+";
+
+/// Quantidade de linhas buscadas por vez durante a iteração
+const TAMANHO_LOTE: i64 = 500;
+
+/// Backend SQLite com acesso indexado por ID e email
+///
+/// O `id` é a chave primária (índice do próprio SQLite) e usa
+/// AUTOINCREMENT, então IDs nunca são reaproveitados.
+#[derive(Debug)]
+pub struct SqliteBackend {
+    /// Caminho do banco de dados
+    caminho: PathBuf,
+
+    /// Conexão aberta com o banco
+    conexao: Connection,
+
+    /// Tempo máximo de espera com o banco ocupado
+    tempo_limite_bloqueio: Duration,
+
+    /// Se o email deve ser único entre as pessoas
+    email_unico: bool,
+
+    /// Quem faz as alterações, gravado no histórico
+    ator: Option<String>,
+}
+
+impl SqliteBackend {
+    /// Abre (ou cria) o banco de dados
+    ///
+    /// Na criação, se existir um CSV de origem (`?csv=` no DSN ou o arquivo
+    /// com o mesmo nome e extensão `.csv`), os registros são migrados
+    /// preservando os IDs.
+    pub fn new(
+        caminho: &str,
+        csv_origem: Option<&str>,
+        tempo_limite_bloqueio: Duration,
+    ) -> PhpResult<Self> {
+        let caminho = PathBuf::from(caminho);
+        let banco_novo = !caminho.exists();
+
+        // Cria diretório se não existir
+        if let Some(parent) = caminho.parent() {
+            if !parent.exists() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| ArmazenamentoException::io(
+                        parent, "Erro ao criar diretório", &e
+                    ))?;
+            }
+        }
+
+        let mut conexao = abrir_conexao(&caminho, tempo_limite_bloqueio)?;
+        atualizar_esquema(&mut conexao, &caminho)?;
+
+        let mut backend = Self {
+            caminho: caminho.clone(),
+            conexao,
+            tempo_limite_bloqueio,
+            email_unico: false,
+            ator: None,
+        };
+
+        if banco_novo {
+            let origem = csv_origem
+                .map(PathBuf::from)
+                .unwrap_or_else(|| caminho.with_extension("csv"));
+
+            if origem.is_file() {
+                // Migração incompleta não pode deixar um banco "novo" para trás,
+                // senão a próxima abertura não tentaria migrar de novo
+                if let Err(e) = backend.migrar_csv(&origem, tempo_limite_bloqueio) {
+                    drop(backend);
+                    let _ = std::fs::remove_file(&caminho);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(backend)
+    }
+
+    /// Importa todas as pessoas de um arquivo CSV em uma única transação
+    fn migrar_csv(&mut self, origem: &Path, tempo_limite_bloqueio: Duration) -> PhpResult {
+        let csv = ArquivoBackend::new(
+            &origem.to_string_lossy(),
+            Box::new(FormatoCsv),
+            tempo_limite_bloqueio,
+        )?;
+        self.substituir_todos(csv.listar()?)
+    }
+}
+
+impl StorageBackend for SqliteBackend {
+    fn criar(&mut self, pessoa: &mut Pessoa) -> PhpResult<i64> {
+        let transacao = self.conexao
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(erro_sqlite)?;
+
+        transacao
+            .execute(
+                "INSERT INTO pessoas (nome, email, telefone, deletado_em, versao, documento)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    pessoa.nome,
+                    pessoa.email,
+                    pessoa.telefone,
+                    pessoa.deletado_em,
+                    pessoa.versao,
+                    pessoa.documento
+                ],
+            )
+            .map_err(erro_sqlite)?;
+
+        let novo_id = transacao.last_insert_rowid();
+        if self.email_unico {
+            verificar_email_unico(&transacao, novo_id, &pessoa.email)?;
+        }
+
+        let mut criada = pessoa.clone();
+        criada.definir_id(novo_id);
+        let alteracoes: Vec<Alteracao> =
+            Alteracao::new(None, Some(criada), self.ator.clone()).into_iter().collect();
+        inserir_historico(&transacao, &alteracoes)?;
+
+        transacao.commit().map_err(erro_sqlite)?;
+        pessoa.definir_id(novo_id);
+        Ok(novo_id)
+    }
+
+    fn atualizar(&mut self, pessoa: &Pessoa) -> PhpResult<bool> {
+        let transacao = self.conexao
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(erro_sqlite)?;
+
+        let Some(anterior) = buscar_pessoa(&transacao, pessoa.id.unwrap_or(0))? else {
+            return Ok(false);
+        };
+        verificar_versao(&anterior, pessoa)?;
+
+        transacao
+            .execute(
+                "UPDATE pessoas SET nome = ?1, email = ?2, telefone = ?3, deletado_em = ?4, versao = ?5,
+                 documento = ?6 WHERE id = ?7",
+                params![
+                    pessoa.nome,
+                    pessoa.email,
+                    pessoa.telefone,
+                    pessoa.deletado_em,
+                    pessoa.versao,
+                    pessoa.documento,
+                    pessoa.id
+                ],
+            )
+            .map_err(erro_sqlite)?;
+
+        if self.email_unico {
+            verificar_email_unico(&transacao, pessoa.id.unwrap_or(0), &pessoa.email)?;
+        }
+
+        let alteracoes: Vec<Alteracao> =
+            Alteracao::new(Some(anterior), Some(pessoa.clone()), self.ator.clone()).into_iter().collect();
+        inserir_historico(&transacao, &alteracoes)?;
+
+        transacao.commit().map_err(erro_sqlite)?;
+        Ok(true)
+    }
+
+    fn deletar(&mut self, id: i64) -> PhpResult<bool> {
+        let transacao = self.conexao
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(erro_sqlite)?;
+
+        let Some(anterior) = buscar_pessoa(&transacao, id)? else {
+            return Ok(false);
+        };
+
+        transacao
+            .execute("DELETE FROM pessoas WHERE id = ?1", params![id])
+            .map_err(erro_sqlite)?;
+
+        let alteracoes: Vec<Alteracao> =
+            Alteracao::new(Some(anterior), None, self.ator.clone()).into_iter().collect();
+        inserir_historico(&transacao, &alteracoes)?;
+
+        transacao.commit().map_err(erro_sqlite)?;
+        Ok(true)
+    }
+
+    fn listar(&self) -> PhpResult<Vec<Pessoa>> {
+        listar_pessoas(&self.conexao)
+    }
+
+    fn substituir_todos(&mut self, pessoas: Vec<Pessoa>) -> PhpResult {
+        let transacao = self.conexao.transaction().map_err(erro_sqlite)?;
+        transacao.execute("DELETE FROM pessoas", []).map_err(erro_sqlite)?;
+        {
+            // ID nulo faz o SQLite gerar um novo pelo AUTOINCREMENT
+            let mut insert = transacao
+                .prepare(
+                    "INSERT INTO pessoas (id, nome, email, telefone, deletado_em, versao, documento)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+                )
+                .map_err(erro_sqlite)?;
+
+            for pessoa in &pessoas {
+                insert
+                    .execute(params![
+                        pessoa.id,
+                        pessoa.nome,
+                        pessoa.email,
+                        pessoa.telefone,
+                        pessoa.deletado_em,
+                        pessoa.versao,
+                        pessoa.documento
+                    ])
+                    .map_err(erro_sqlite)?;
+            }
+        }
+        transacao.commit().map_err(erro_sqlite)
+    }
+
+    fn reservar_ids(&mut self, quantidade: i64) -> PhpResult<i64> {
+        let transacao = self.conexao
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(erro_sqlite)?;
+
+        // Mesmo cálculo do AUTOINCREMENT: maior valor entre a sequência e os IDs
+        let atual: i64 = transacao
+            .query_row(
+                "SELECT MAX(
+                    COALESCE((SELECT seq FROM sqlite_sequence WHERE name = 'pessoas'), 0),
+                    COALESCE((SELECT MAX(id) FROM pessoas), 0)
+                )",
+                [],
+                |linha| linha.get(0),
+            )
+            .map_err(erro_sqlite)?;
+
+        // Avançar a sequência reserva a faixa: o AUTOINCREMENT não a reutiliza
+        transacao
+            .execute("DELETE FROM sqlite_sequence WHERE name = 'pessoas'", [])
+            .map_err(erro_sqlite)?;
+        transacao
+            .execute(
+                "INSERT INTO sqlite_sequence (name, seq) VALUES ('pessoas', ?1)",
+                params![atual + quantidade],
+            )
+            .map_err(erro_sqlite)?;
+
+        transacao.commit().map_err(erro_sqlite)?;
+        Ok(atual + 1)
+    }
+
+    fn aplicar(&mut self, operacoes: Vec<Operacao>) -> PhpResult {
+        let transacao = self.conexao
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(erro_sqlite)?;
+
+        // Estado anterior lido dentro da transação, para o histórico
+        let anteriores = pessoas_afetadas(&transacao, &operacoes)?;
+        let alteracoes = alteracoes_das_operacoes(&anteriores, &operacoes, &self.ator);
+
+        // Qualquer erro descarta a transação (rollback no drop)
+        let mut alteradas = Vec::new();
+        for operacao in &operacoes {
+            match operacao {
+                Operacao::Criar(pessoa) => {
+                    alteradas.push(pessoa);
+                    transacao
+                        .execute(
+                            "INSERT INTO pessoas (id, nome, email, telefone, deletado_em, versao, documento)
+                             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                            params![
+                                pessoa.id,
+                                pessoa.nome,
+                                pessoa.email,
+                                pessoa.telefone,
+                                pessoa.deletado_em,
+                                pessoa.versao,
+                                pessoa.documento
+                            ],
+                        )
+                        .map_err(erro_sqlite)?;
+                }
+                Operacao::Atualizar(pessoa) => {
+                    if !verificar_versao_armazenada(&transacao, pessoa)? {
+                        return Err(nao_encontrada(pessoa.id.unwrap_or(0)));
+                    }
+
+                    transacao
+                        .execute(
+                            "UPDATE pessoas SET nome = ?1, email = ?2, telefone = ?3, deletado_em = ?4,
+                             versao = ?5, documento = ?6 WHERE id = ?7",
+                            params![
+                                pessoa.nome,
+                                pessoa.email,
+                                pessoa.telefone,
+                                pessoa.deletado_em,
+                                pessoa.versao,
+                                pessoa.documento,
+                                pessoa.id
+                            ],
+                        )
+                        .map_err(erro_sqlite)?;
+                    alteradas.push(pessoa);
+                }
+                Operacao::Deletar(id) => {
+                    transacao
+                        .execute("DELETE FROM pessoas WHERE id = ?1", params![id])
+                        .map_err(erro_sqlite)?;
+                }
+                Operacao::Limpar => {
+                    transacao
+                        .execute("DELETE FROM pessoas", [])
+                        .map_err(erro_sqlite)?;
+                }
+            }
+        }
+
+        // Verificada só no fim: a transação pode trocar emails entre pessoas
+        if self.email_unico {
+            for pessoa in alteradas {
+                verificar_email_unico(&transacao, pessoa.id.unwrap_or(0), &pessoa.email)?;
+            }
+        }
+
+        inserir_historico(&transacao, &alteracoes)?;
+        transacao.commit().map_err(erro_sqlite)
+    }
+
+    fn caminho(&self) -> String {
+        self.caminho.to_string_lossy().into_owned()
+    }
+
+    fn iterar(&self) -> PhpResult<FluxoPessoas> {
+        // Conexão própria: o iterador sobrevive a chamadas deste backend
+        let conexao = abrir_conexao(&self.caminho, self.tempo_limite_bloqueio)?;
+
+        // Pessoas criadas depois deste ponto ficam de fora, como no CSV
+        let maior_id: i64 = conexao
+            .query_row("SELECT COALESCE(MAX(id), 0) FROM pessoas", [], |linha| linha.get(0))
+            .map_err(erro_sqlite)?;
+
+        Ok(Box::new(IteradorSqlite {
+            conexao,
+            ultimo_id: i64::MIN,
+            maior_id,
+            lote: VecDeque::new(),
+            fim: false,
+        }))
+    }
+
+    fn buscar(&self, id: i64) -> PhpResult<Option<Pessoa>> {
+        buscar_pessoa(&self.conexao, id)
+    }
+
+    fn contar(&self) -> PhpResult<i64> {
+        self.conexao
+            .query_row("SELECT COUNT(*) FROM pessoas WHERE deletado_em IS NULL", [], |linha| linha.get(0))
+            .map_err(erro_sqlite)
+    }
+
+    fn historico(&self, id: i64) -> PhpResult<Vec<Alteracao>> {
+        let mut consulta = self.conexao
+            .prepare(
+                "SELECT id_pessoa, operacao, anterior, nova, momento, ator
+                 FROM historico WHERE id_pessoa = ?1 ORDER BY seq"
+            )
+            .map_err(erro_sqlite)?;
+
+        let alteracoes = consulta
+            .query_map(params![id], alteracao_de_linha)
+            .map_err(erro_sqlite)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(erro_sqlite)?;
+
+        Ok(alteracoes)
+    }
+
+    fn definir_email_unico(&mut self, ativo: bool) {
+        self.email_unico = ativo;
+    }
+
+    fn definir_ator(&mut self, ator: Option<String>) {
+        self.ator = ator;
+    }
+
+    fn definir_tempo_limite_bloqueio(&mut self, tempo_limite: Duration) {
+        self.tempo_limite_bloqueio = tempo_limite;
+        let _ = self.conexao.busy_timeout(tempo_limite);
+    }
+}
+
+/// Percorre a tabela em lotes ordenados por ID (paginação por chave),
+/// mantendo em memória no máximo `TAMANHO_LOTE` pessoas
+struct IteradorSqlite {
+    conexao: Connection,
+    ultimo_id: i64,
+    maior_id: i64,
+    lote: VecDeque<Pessoa>,
+    fim: bool,
+}
+
+impl IteradorSqlite {
+    /// Busca o próximo lote a partir do último ID emitido
+    fn carregar_lote(&mut self) -> PhpResult {
+        let mut consulta = self.conexao
+            .prepare_cached(
+                "SELECT id, nome, email, telefone, deletado_em, versao, documento FROM pessoas
+                 WHERE id > ?1 AND id <= ?2 ORDER BY id LIMIT ?3",
+            )
+            .map_err(erro_sqlite)?;
+
+        self.lote = consulta
+            .query_map(params![self.ultimo_id, self.maior_id, TAMANHO_LOTE], pessoa_de_linha)
+            .map_err(erro_sqlite)?
+            .collect::<rusqlite::Result<_>>()
+            .map_err(erro_sqlite)?;
+
+        self.fim = (self.lote.len() as i64) < TAMANHO_LOTE;
+        Ok(())
+    }
+}
+
+impl Iterator for IteradorSqlite {
+    type Item = PhpResult<Pessoa>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.lote.is_empty() && !self.fim {
+            if let Err(e) = self.carregar_lote() {
+                self.fim = true;
+                return Some(Err(e));
+            }
+        }
+
+        let pessoa = self.lote.pop_front()?;
+        self.ultimo_id = pessoa.id.unwrap_or(self.ultimo_id);
+        Some(Ok(pessoa))
+    }
+}
+
+/// Colunas criadas em versões posteriores, com sua definição
+const COLUNAS_NOVAS: [(&str, &str); 3] = [
+    ("deletado_em", "INTEGER"),
+    ("versao", "INTEGER NOT NULL DEFAULT 0"),
+    ("documento", "TEXT"),
+];
+
+/// Passos de migração únicos: cada um roda em bancos com `user_version`
+/// abaixo da versão indicada
+const MIGRACOES: [(i64, &str); 1] = [
+    // O índice de email passou a ser sobre o email normalizado, o mesmo
+    // usado na verificação de unicidade
+    (4, "
+        DROP INDEX IF EXISTS idx_pessoas_email;
+        CREATE INDEX IF NOT EXISTS idx_pessoas_email_normalizado ON pessoas (lower(trim(email)));
+    "),
+];
+
+/// Cria ou atualiza a estrutura do banco e registra a versão do esquema em
+/// `PRAGMA user_version`
+///
+/// Um banco já no esquema atual só é lido: nenhuma abertura depois da
+/// primeira toma o bloqueio de escrita. Os demais são atualizados numa
+/// única transação, com passos que podem ser repetidos sem efeito caso
+/// outro processo faça a mesma atualização ao mesmo tempo
+fn atualizar_esquema(conexao: &mut Connection, caminho: &Path) -> PhpResult {
+    let versao: i64 = conexao
+        .pragma_query_value(None, "user_version", |linha| linha.get(0))
+        .map_err(erro_sqlite)?;
+
+    if versao > VERSAO_ESQUEMA {
+        return Err(ArmazenamentoException::dados_invalidos(caminho, format!(
+            "Banco no esquema {}, mais novo que o suportado ({})",
+            versao, VERSAO_ESQUEMA
+        )));
+    }
+    if versao == VERSAO_ESQUEMA {
+        return Ok(());
+    }
+
+    let transacao = conexao
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(erro_sqlite)?;
+    transacao.execute_batch(ESQUEMA).map_err(erro_sqlite)?;
+
+    // Tabelas criadas antes de cada coluna não a ganham pelo CREATE TABLE
+    for (coluna, definicao) in COLUNAS_NOVAS {
+        let existe: bool = transacao
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('pessoas') WHERE name = ?1",
+                params![coluna],
+                |linha| linha.get(0),
+            )
+            .map_err(erro_sqlite)?;
+
+        if !existe {
+            transacao
+                .execute(&format!("ALTER TABLE pessoas ADD COLUMN {} {}", coluna, definicao), [])
+                .map_err(erro_sqlite)?;
+        }
+    }
+
+    for (ate, passo) in MIGRACOES {
+        if versao < ate {
+            transacao.execute_batch(passo).map_err(erro_sqlite)?;
+        }
+    }
+
+    transacao
+        .pragma_update(None, "user_version", VERSAO_ESQUEMA)
+        .map_err(erro_sqlite)?;
+    transacao.commit().map_err(erro_sqlite)
+}
+
+/// Todas as pessoas, em ordem de ID
+fn listar_pessoas(conexao: &Connection) -> PhpResult<Vec<Pessoa>> {
+    let mut consulta = conexao
+        .prepare("SELECT id, nome, email, telefone, deletado_em, versao, documento FROM pessoas ORDER BY id")
+        .map_err(erro_sqlite)?;
+
+    let pessoas = consulta
+        .query_map([], pessoa_de_linha)
+        .map_err(erro_sqlite)?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(erro_sqlite)?;
+
+    Ok(pessoas)
+}
+
+/// Pessoa com o ID informado, se existir
+fn buscar_pessoa(conexao: &Connection, id: i64) -> PhpResult<Option<Pessoa>> {
+    conexao
+        .query_row(
+            "SELECT id, nome, email, telefone, deletado_em, versao, documento FROM pessoas WHERE id = ?1",
+            params![id],
+            pessoa_de_linha,
+        )
+        .optional()
+        .map_err(erro_sqlite)
+}
+
+/// Estado atual das pessoas que as operações alteram ou removem (todas,
+/// se houver uma limpeza), base do histórico gerado por elas
+fn pessoas_afetadas(conexao: &Connection, operacoes: &[Operacao]) -> PhpResult<Vec<Pessoa>> {
+    if operacoes.iter().any(|op| matches!(op, Operacao::Limpar)) {
+        return listar_pessoas(conexao);
+    }
+
+    let mut ids: Vec<i64> = operacoes
+        .iter()
+        .filter_map(|op| match op {
+            Operacao::Atualizar(pessoa) => pessoa.id,
+            Operacao::Deletar(id) => Some(*id),
+            _ => None,
+        })
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut pessoas = Vec::with_capacity(ids.len());
+    for id in ids {
+        pessoas.extend(buscar_pessoa(conexao, id)?);
+    }
+    Ok(pessoas)
+}
+
+/// Acrescenta alterações ao histórico, dentro da transação do chamador
+fn inserir_historico(conexao: &Connection, alteracoes: &[Alteracao]) -> PhpResult {
+    let mut insert = conexao
+        .prepare_cached(
+            "INSERT INTO historico (id_pessoa, operacao, anterior, nova, momento, ator)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+        )
+        .map_err(erro_sqlite)?;
+
+    // As versões da pessoa são guardadas como JSON
+    let json = |pessoa: &Option<Pessoa>| pessoa.as_ref().map(|p| {
+        serde_json::to_string(p).expect("Pessoa sempre é serializável em JSON")
+    });
+
+    for alteracao in alteracoes {
+        insert
+            .execute(params![
+                alteracao.id_pessoa,
+                alteracao.operacao,
+                json(&alteracao.anterior),
+                json(&alteracao.nova),
+                alteracao.momento,
+                alteracao.ator
+            ])
+            .map_err(erro_sqlite)?;
+    }
+    Ok(())
+}
+
+/// Confere se a versão armazenada é a anterior à da pessoa recebida
+/// Retorna `false` se a pessoa não existir
+fn verificar_versao_armazenada(conexao: &Connection, pessoa: &Pessoa) -> PhpResult<bool> {
+    let atual: Option<i64> = conexao
+        .query_row(
+            "SELECT versao FROM pessoas WHERE id = ?1",
+            params![pessoa.id],
+            |linha| linha.get(0),
+        )
+        .optional()
+        .map_err(erro_sqlite)?;
+
+    match atual {
+        None => Ok(false),
+        Some(atual) if atual + 1 != pessoa.versao => Err(conflito_versao(
+            pessoa.id.unwrap_or(0),
+            pessoa.versao - 1,
+            atual,
+        )),
+        Some(_) => Ok(true),
+    }
+}
+
+/// Falha se outra pessoa (ID diferente de `id`) usar o mesmo email
+/// Não verifica nada se `id` já não existir (removido na mesma transação)
+/// A comparação ignora espaços nas pontas e maiúsculas, como nos demais backends,
+/// e usa o índice sobre a mesma expressão (`idx_pessoas_email_normalizado`)
+fn verificar_email_unico(conexao: &Connection, id: i64, email: &str) -> PhpResult {
+    let dono: Option<i64> = conexao
+        .query_row(
+            "SELECT id FROM pessoas
+             WHERE lower(trim(email)) = ?1 AND id != ?2
+               AND EXISTS (SELECT 1 FROM pessoas WHERE id = ?2)
+             LIMIT 1",
+            params![normalizar_email(email), id],
+            |linha| linha.get(0),
+        )
+        .optional()
+        .map_err(erro_sqlite)?;
+
+    match dono {
+        Some(dono) => Err(email_duplicado(email, Some(dono))),
+        None => Ok(()),
+    }
+}
+
+/// Abre uma conexão com o tempo de espera por bloqueio configurado
+fn abrir_conexao(caminho: &Path, tempo_limite_bloqueio: Duration) -> PhpResult<Connection> {
+    let conexao = Connection::open(caminho).map_err(erro_sqlite)?;
+    conexao.busy_timeout(tempo_limite_bloqueio).map_err(erro_sqlite)?;
+    Ok(conexao)
+}
+
+/// Converte uma linha do SELECT padrão em Pessoa
+fn pessoa_de_linha(linha: &Row) -> rusqlite::Result<Pessoa> {
+    Ok(Pessoa {
+        id: linha.get(0)?,
+        nome: linha.get(1)?,
+        email: linha.get(2)?,
+        telefone: linha.get(3)?,
+        deletado_em: linha.get(4)?,
+        versao: linha.get(5)?,
+        documento: linha.get(6)?,
+    })
+}
+
+/// Monta uma alteração a partir de uma linha
+/// (colunas: id_pessoa, operacao, anterior, nova, momento, ator)
+fn alteracao_de_linha(linha: &Row) -> rusqlite::Result<Alteracao> {
+    let versao = |coluna: usize| -> rusqlite::Result<Option<Pessoa>> {
+        linha
+            .get::<_, Option<String>>(coluna)?
+            .map(|json| serde_json::from_str(&json).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(coluna, Type::Text, Box::new(e))
+            }))
+            .transpose()
+    };
+
+    Ok(Alteracao {
+        id_pessoa: linha.get(0)?,
+        operacao: linha.get(1)?,
+        anterior: versao(2)?,
+        nova: versao(3)?,
+        momento: linha.get(4)?,
+        ator: linha.get(5)?,
+    })
+}
+
+/// Converte erros do SQLite em exceções PHP
+/// Banco ocupado além do tempo limite vira BloqueioException, como no CSV;
+/// banco corrompido ou que não é SQLite é sinalizado como dados inválidos
+fn erro_sqlite(e: rusqlite::Error) -> PhpException {
+    match e.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => {
+            BloqueioException::erro(format!("Banco de dados ocupado: {}", e))
+        }
+        Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => ArmazenamentoException::erro(
+            WeadException::CODIGO_DADOS_INVALIDOS,
+            format!("Banco de dados inválido: {}", e)
+        ),
+        _ => ArmazenamentoException::erro(
+            WeadException::CODIGO_ARMAZENAMENTO,
+            format!("Erro no SQLite: {}", e)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Caminho de um banco num diretório temporário exclusivo (sem CSV ao lado)
+    fn caminho(nome: &str) -> PathBuf {
+        let diretorio = std::env::temp_dir().join(format!("wead_sqlite_{}_{}", std::process::id(), nome));
+        let _ = std::fs::remove_dir_all(&diretorio);
+        std::fs::create_dir_all(&diretorio).unwrap();
+        diretorio.join("b.db")
+    }
+
+    fn abrir(caminho: &Path) -> PhpResult<SqliteBackend> {
+        SqliteBackend::new(caminho.to_str().unwrap(), None, Duration::from_millis(100))
+    }
+
+    fn indices(conexao: &Connection) -> Vec<String> {
+        let mut consulta = conexao
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'pessoas' ORDER BY name")
+            .unwrap();
+        consulta.query_map([], |linha| linha.get(0)).unwrap().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn banco_no_esquema_atual_abre_sem_escrever() {
+        let caminho = caminho("atual");
+        drop(abrir(&caminho).unwrap());
+
+        // Outro processo segura o bloqueio de escrita durante a abertura
+        let outro = Connection::open(&caminho).unwrap();
+        outro.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+        let backend = abrir(&caminho).unwrap();
+        assert_eq!(backend.listar().unwrap().len(), 0);
+        outro.execute_batch("COMMIT").unwrap();
+    }
+
+    #[test]
+    fn banco_antigo_migrado_uma_vez() {
+        let caminho = caminho("antigo");
+        Connection::open(&caminho).unwrap().execute_batch("
+            CREATE TABLE pessoas (
+                id       INTEGER PRIMARY KEY AUTOINCREMENT,
+                nome     TEXT NOT NULL,
+                email    TEXT NOT NULL,
+                telefone TEXT NOT NULL
+            );
+            CREATE INDEX idx_pessoas_email ON pessoas (email);
+            INSERT INTO pessoas (nome, email, telefone) VALUES ('Ana', 'ana@x.com', '1');
+        ").unwrap();
+
+        let backend = abrir(&caminho).unwrap();
+        let versao: i64 = backend.conexao
+            .pragma_query_value(None, "user_version", |linha| linha.get(0))
+            .unwrap();
+        assert_eq!(versao, VERSAO_ESQUEMA);
+        assert_eq!(indices(&backend.conexao), ["idx_pessoas_email_normalizado"]);
+
+        let ana = backend.buscar(1).unwrap().unwrap();
+        assert_eq!((ana.nome.as_str(), ana.versao, ana.documento), ("Ana", 0, None));
+    }
+
+    #[test]
+    fn banco_mais_novo_recusado() {
+        let caminho = caminho("novo");
+        Connection::open(&caminho).unwrap()
+            .pragma_update(None, "user_version", VERSAO_ESQUEMA + 1)
+            .unwrap();
+
+        assert!(abrir(&caminho).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// BLOQUEIO
+// Bloqueios consultivos (flock) entre processos para o arquivo de dados
+// ============================================================================
+
+/// Intervalo entre tentativas enquanto o bloqueio está ocupado
+const INTERVALO_TENTATIVA: Duration = Duration::from_millis(5);
+
+/// Tipo de bloqueio solicitado
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModoBloqueio {
+    /// Vários leitores simultâneos
+    Compartilhado,
+
+    /// Um único escritor, sem leitores
+    Exclusivo,
+}
+
+/// Erros possíveis ao adquirir um bloqueio
+#[derive(Debug)]
+pub enum ErroBloqueio {
+    /// O bloqueio não foi obtido dentro do tempo limite
+    TempoEsgotado(Duration),
+
+    /// Falha de E/S ao abrir ou bloquear o arquivo
+    Io(io::Error),
+}
+
+/// Bloqueio adquirido; é liberado automaticamente ao sair de escopo
+#[derive(Debug)]
+pub struct Bloqueio {
+    arquivo: File,
+}
+
+impl Bloqueio {
+    /// Adquire um bloqueio sobre o arquivo auxiliar `<caminho>.lock`
+    ///
+    /// O bloqueio fica em um arquivo separado para que continue válido
+    /// mesmo quando o arquivo de dados é recriado ou substituído.
+    pub fn adquirir(
+        caminho: &Path,
+        modo: ModoBloqueio,
+        tempo_limite: Duration,
+    ) -> Result<Self, ErroBloqueio> {
+        let arquivo = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(caminho_bloqueio(caminho))
+            .map_err(ErroBloqueio::Io)?;
+
+        let inicio = Instant::now();
+
+        loop {
+            let tentativa = match modo {
+                ModoBloqueio::Compartilhado => arquivo.try_lock_shared(),
+                ModoBloqueio::Exclusivo => arquivo.try_lock(),
+            };
+
+            match tentativa {
+                Ok(()) => return Ok(Self { arquivo }),
+                Err(std::fs::TryLockError::WouldBlock) => {
+                    if inicio.elapsed() >= tempo_limite {
+                        return Err(ErroBloqueio::TempoEsgotado(tempo_limite));
+                    }
+                    thread::sleep(INTERVALO_TENTATIVA);
+                }
+                Err(std::fs::TryLockError::Error(e)) => return Err(ErroBloqueio::Io(e)),
+            }
+        }
+    }
+}
+
+impl Drop for Bloqueio {
+    fn drop(&mut self) {
+        let _ = self.arquivo.unlock();
+    }
+}
+
+/// Caminho do arquivo auxiliar de bloqueio
+pub fn caminho_bloqueio(caminho: &Path) -> PathBuf {
+    let mut nome = caminho.as_os_str().to_owned();
+    nome.push(".lock");
+    PathBuf::from(nome)
+}
@@ -0,0 +1,113 @@
+use unicode_normalization::UnicodeNormalization;
+
+// ============================================================================
+// COLAÇÃO pt-BR
+// Ordenação de textos como esperada por usuários brasileiros
+// ============================================================================
+
+/// Chave de ordenação de um texto segundo a colação pt-BR
+///
+/// Os níveis seguem a mesma ideia do ICU/CLDR:
+/// 1. letras base, sem acento e sem diferenciar maiúsculas (`É` = `e`)
+/// 2. acentos (`e` < `é` < `ê`), só desempatam textos iguais no nível 1
+/// 3. caixa (minúscula antes de maiúscula), só desempata o nível 2
+///
+/// O texto é normalizado (NFC) antes: `É` composto e `E` + acento
+/// combinante (U+0301) têm a mesma chave
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChaveColacao {
+    base: String,
+    acentos: Vec<u8>,
+    caixa: Vec<u8>,
+}
+
+impl ChaveColacao {
+    /// Calcula a chave de um texto
+    pub fn new(texto: &str) -> Self {
+        let mut chave = Self {
+            base: String::with_capacity(texto.len()),
+            acentos: Vec::new(),
+            caixa: Vec::new(),
+        };
+
+        for c in texto.nfc() {
+            let maiuscula = c.is_uppercase();
+            let minuscula: String = c.to_lowercase().collect();
+
+            for c in minuscula.chars() {
+                let acento = decompor(c, &mut chave.base);
+                chave.acentos.push(acento);
+                chave.caixa.push(maiuscula as u8);
+            }
+        }
+
+        chave
+    }
+}
+
+/// Acrescenta a(s) letra(s) base de uma letra minúscula e retorna o peso
+/// do acento, na ordem usual dos dicionários: agudo, grave, circunflexo,
+/// til, trema
+fn decompor(c: char, base: &mut String) -> u8 {
+    let (letra, acento) = match c {
+        'á' => ('a', 1), 'à' => ('a', 2), 'â' => ('a', 3), 'ã' => ('a', 4), 'ä' => ('a', 5), 'å' => ('a', 6),
+        'é' => ('e', 1), 'è' => ('e', 2), 'ê' => ('e', 3), 'ẽ' => ('e', 4), 'ë' => ('e', 5),
+        'í' => ('i', 1), 'ì' => ('i', 2), 'î' => ('i', 3), 'ĩ' => ('i', 4), 'ï' => ('i', 5),
+        'ó' => ('o', 1), 'ò' => ('o', 2), 'ô' => ('o', 3), 'õ' => ('o', 4), 'ö' => ('o', 5),
+        'ú' => ('u', 1), 'ù' => ('u', 2), 'û' => ('u', 3), 'ũ' => ('u', 4), 'ü' => ('u', 5),
+        'ý' => ('y', 1), 'ÿ' => ('y', 5),
+        'ç' => ('c', 7),
+        'ñ' => ('n', 4),
+        // Ligaduras contam como duas letras
+        'ß' => return decompor_ligadura("ss", base),
+        'æ' => return decompor_ligadura("ae", base),
+        'œ' => return decompor_ligadura("oe", base),
+        _ => (c, 0),
+    };
+
+    base.push(letra);
+    acento
+}
+
+/// Acrescenta as letras de uma ligadura (sem acento)
+fn decompor_ligadura(letras: &str, base: &mut String) -> u8 {
+    base.push_str(letras);
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Textos na ordem da colação
+    fn ordenados(textos: &[&str]) -> Vec<String> {
+        let mut ordenados: Vec<String> = textos.iter().map(|t| t.to_string()).collect();
+        ordenados.sort_by_cached_key(|t| ChaveColacao::new(t));
+        ordenados
+    }
+
+    #[test]
+    fn acentos_nao_separam_as_letras() {
+        assert_eq!(ordenados(&["Zé", "Érico", "Eva", "ana", "Álvaro"]), ["Álvaro", "ana", "Érico", "Eva", "Zé"]);
+        assert_eq!(ordenados(&["Ço", "Cz", "Ca"]), ["Ca", "Ço", "Cz"]);
+    }
+
+    #[test]
+    fn desempate_por_acento_e_caixa() {
+        assert_eq!(ordenados(&["pêra", "Pera", "pera", "péra"]), ["pera", "Pera", "péra", "pêra"]);
+    }
+
+    #[test]
+    fn forma_decomposta_igual_a_composta() {
+        let decomposto = "E\u{301}der";
+        assert_eq!(ChaveColacao::new(decomposto), ChaveColacao::new("Éder"));
+        assert_eq!(ordenados(&["Ezra", decomposto]), [decomposto, "Ezra"]);
+        assert_eq!(ordenados(&["Ezra", "Éder"]), ["Éder", "Ezra"]);
+    }
+
+    #[test]
+    fn ligaduras_como_duas_letras() {
+        assert_eq!(ChaveColacao::new("Straße").base, "strasse");
+        assert_eq!(ordenados(&["Æsir", "Adão", "Afonso"]), ["Adão", "Æsir", "Afonso"]);
+    }
+}
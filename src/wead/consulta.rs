@@ -0,0 +1,355 @@
+use ext_php_rs::prelude::*;
+use ext_php_rs::types::ZendClassObject;
+
+use super::{Pessoa, ValidacaoException};
+
+// ============================================================================
+// CLASSE: Consulta
+// Filtro de pessoas montado em PHP e avaliado em Rust
+// ============================================================================
+
+/// Campo de Pessoa que pode ser filtrado
+#[derive(Debug, Clone, Copy)]
+enum Campo {
+    Nome,
+    Email,
+    Telefone,
+    Documento,
+}
+
+impl Campo {
+    /// Interpreta o nome do campo recebido do PHP
+    fn de_nome(nome: &str) -> PhpResult<Self> {
+        match nome.to_lowercase().as_str() {
+            "nome" => Ok(Self::Nome),
+            "email" => Ok(Self::Email),
+            "telefone" => Ok(Self::Telefone),
+            "documento" => Ok(Self::Documento),
+            _ => Err(ValidacaoException::erro(
+                "campo",
+                format!("Campo de consulta inválido: {} (use nome, email, telefone ou documento)", nome)
+            )),
+        }
+    }
+
+    /// Valor do campo na pessoa (documento ausente vale "")
+    fn valor(self, pessoa: &Pessoa) -> &str {
+        match self {
+            Self::Nome => &pessoa.nome,
+            Self::Email => &pessoa.email,
+            Self::Telefone => &pessoa.telefone,
+            Self::Documento => pessoa.documento.as_deref().unwrap_or_default(),
+        }
+    }
+}
+
+/// Condição sobre um campo
+#[derive(Debug, Clone)]
+enum Condicao {
+    /// Valor exatamente igual
+    Igual(Campo, String),
+
+    /// Contém o trecho (sem diferenciar maiúsculas)
+    Contem(Campo, String),
+
+    /// Começa com o prefixo (sem diferenciar maiúsculas)
+    ComecaCom(Campo, String),
+
+    /// Igual a algum dos valores da lista
+    Em(Campo, Vec<String>),
+}
+
+impl Condicao {
+    /// Verifica se a pessoa satisfaz a condição
+    fn aceita(&self, pessoa: &Pessoa) -> bool {
+        match self {
+            Self::Igual(campo, valor) => campo.valor(pessoa) == valor,
+            Self::Contem(campo, trecho) => campo.valor(pessoa).to_lowercase().contains(trecho),
+            Self::ComecaCom(campo, prefixo) => campo.valor(pessoa).to_lowercase().starts_with(prefixo),
+            Self::Em(campo, valores) => valores.iter().any(|v| v == campo.valor(pessoa)),
+        }
+    }
+}
+
+/// Consulta de pessoas para `Storage::consultar()`
+/// Condições seguidas são combinadas com AND; `ou()` inicia um novo grupo,
+/// e a pessoa é aceita se satisfizer todas as condições de algum grupo
+/// Equivalente PHP:
+/// ```php
+/// $consulta = (new Wead\Consulta())
+///     ->comecaCom('nome', 'ana')->contem('email', '@empresa')
+///     ->ou()
+///     ->em('telefone', ['11999990000', '11988880000'])
+///     ->limite(20)->offset(40);
+/// ```
+#[php_class]
+#[php(name = "Wead\\Consulta")]
+#[derive(Debug, Clone, Default)]
+pub struct Consulta {
+    /// Grupos de condições (AND dentro do grupo, OR entre grupos)
+    grupos: Vec<Vec<Condicao>>,
+
+    /// Quantidade máxima de resultados (None = sem limite)
+    limite: Option<i64>,
+
+    /// Quantidade de resultados a pular
+    offset: i64,
+}
+
+#[php_impl]
+impl Consulta {
+    /// Cria uma consulta sem condições (aceita todas as pessoas)
+    pub fn __construct() -> Self {
+        Self::default()
+    }
+
+    /// Exige que o campo seja exatamente igual ao valor
+    /// @param string $campo nome, email, telefone ou documento (sem pontuação)
+    /// @param string $valor
+    /// @return Consulta
+    /// @throws ValidacaoException Se o campo for inválido
+    pub fn igual(
+        self_: &mut ZendClassObject<Consulta>,
+        campo: String,
+        valor: String,
+    ) -> PhpResult<&mut ZendClassObject<Consulta>> {
+        let campo = Campo::de_nome(&campo)?;
+        self_.adicionar(Condicao::Igual(campo, valor));
+        Ok(self_)
+    }
+
+    /// Exige que o campo contenha o trecho (sem diferenciar maiúsculas)
+    /// @param string $campo nome, email, telefone ou documento (sem pontuação)
+    /// @param string $trecho
+    /// @return Consulta
+    /// @throws ValidacaoException Se o campo for inválido
+    pub fn contem(
+        self_: &mut ZendClassObject<Consulta>,
+        campo: String,
+        trecho: String,
+    ) -> PhpResult<&mut ZendClassObject<Consulta>> {
+        let campo = Campo::de_nome(&campo)?;
+        self_.adicionar(Condicao::Contem(campo, trecho.to_lowercase()));
+        Ok(self_)
+    }
+
+    /// Exige que o campo comece com o prefixo (sem diferenciar maiúsculas)
+    /// @param string $campo nome, email, telefone ou documento (sem pontuação)
+    /// @param string $prefixo
+    /// @return Consulta
+    /// @throws ValidacaoException Se o campo for inválido
+    pub fn comeca_com(
+        self_: &mut ZendClassObject<Consulta>,
+        campo: String,
+        prefixo: String,
+    ) -> PhpResult<&mut ZendClassObject<Consulta>> {
+        let campo = Campo::de_nome(&campo)?;
+        self_.adicionar(Condicao::ComecaCom(campo, prefixo.to_lowercase()));
+        Ok(self_)
+    }
+
+    /// Exige que o campo seja igual a algum dos valores
+    /// @param string $campo nome, email, telefone ou documento (sem pontuação)
+    /// @param array $valores Lista de strings
+    /// @return Consulta
+    /// @throws ValidacaoException Se o campo for inválido
+    pub fn em(
+        self_: &mut ZendClassObject<Consulta>,
+        campo: String,
+        valores: Vec<String>,
+    ) -> PhpResult<&mut ZendClassObject<Consulta>> {
+        let campo = Campo::de_nome(&campo)?;
+        self_.adicionar(Condicao::Em(campo, valores));
+        Ok(self_)
+    }
+
+    /// Inicia um novo grupo de condições, combinado com os anteriores por OR
+    /// @return Consulta
+    pub fn ou(self_: &mut ZendClassObject<Consulta>) -> &mut ZendClassObject<Consulta> {
+        if self_.grupos.last().is_some_and(|grupo| !grupo.is_empty()) {
+            self_.grupos.push(Vec::new());
+        }
+        self_
+    }
+
+    /// Limita a quantidade de resultados
+    /// @param int $limite
+    /// @return Consulta
+    /// @throws ValidacaoException Se o valor for negativo
+    pub fn limite(
+        self_: &mut ZendClassObject<Consulta>,
+        limite: i64,
+    ) -> PhpResult<&mut ZendClassObject<Consulta>> {
+        if limite < 0 {
+            return Err(ValidacaoException::erro("limite", "Limite não pode ser negativo".into()));
+        }
+        self_.limite = Some(limite);
+        Ok(self_)
+    }
+
+    /// Pula os primeiros resultados
+    /// @param int $offset
+    /// @return Consulta
+    /// @throws ValidacaoException Se o valor for negativo
+    pub fn offset(
+        self_: &mut ZendClassObject<Consulta>,
+        offset: i64,
+    ) -> PhpResult<&mut ZendClassObject<Consulta>> {
+        if offset < 0 {
+            return Err(ValidacaoException::erro("offset", "Offset não pode ser negativo".into()));
+        }
+        self_.offset = offset;
+        Ok(self_)
+    }
+}
+
+impl Consulta {
+    /// Acrescenta uma condição ao grupo atual
+    fn adicionar(&mut self, condicao: Condicao) {
+        match self.grupos.last_mut() {
+            Some(grupo) => grupo.push(condicao),
+            None => self.grupos.push(vec![condicao]),
+        }
+    }
+
+    /// Verifica se a pessoa satisfaz a consulta (sem considerar limite/offset)
+    pub fn aceita(&self, pessoa: &Pessoa) -> bool {
+        let mut grupos = self.grupos.iter().filter(|grupo| !grupo.is_empty()).peekable();
+
+        // Sem condições, todas as pessoas são aceitas
+        if grupos.peek().is_none() {
+            return true;
+        }
+        grupos.any(|grupo| grupo.iter().all(|condicao| condicao.aceita(pessoa)))
+    }
+
+    /// Aplica filtro, offset e limite sobre um fluxo de pessoas,
+    /// parando de ler assim que o limite é atingido
+    pub fn aplicar<I>(&self, pessoas: I) -> PhpResult<Vec<Pessoa>>
+    where
+        I: Iterator<Item = PhpResult<Pessoa>>,
+    {
+        let limite = self.limite.map_or(usize::MAX, |limite| limite as usize);
+        let mut resultado = Vec::new();
+        let mut pular = self.offset;
+
+        if limite == 0 {
+            return Ok(resultado);
+        }
+
+        for pessoa in pessoas {
+            let pessoa = pessoa?;
+            if !self.aceita(&pessoa) {
+                continue;
+            }
+            if pular > 0 {
+                pular -= 1;
+                continue;
+            }
+
+            resultado.push(pessoa);
+            if resultado.len() >= limite {
+                break;
+            }
+        }
+
+        Ok(resultado)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn pessoa(nome: &str, email: &str, documento: Option<&str>) -> Pessoa {
+        let mut pessoa = Pessoa::__construct(nome.into(), email.into(), "11987654321".into());
+        pessoa.documento = documento.map(String::from);
+        pessoa
+    }
+
+    /// Consulta com os grupos informados (como montada por `ou()`)
+    fn consulta(grupos: Vec<Vec<Condicao>>) -> Consulta {
+        Consulta { grupos, ..Consulta::default() }
+    }
+
+    fn nomes(pessoas: &[Pessoa]) -> Vec<&str> {
+        pessoas.iter().map(|p| p.nome.as_str()).collect()
+    }
+
+    #[test]
+    fn campos_sem_diferenciar_maiusculas() {
+        assert!(matches!(Campo::de_nome("Email"), Ok(Campo::Email)));
+        assert!(matches!(Campo::de_nome("DOCUMENTO"), Ok(Campo::Documento)));
+    }
+
+    #[test]
+    fn sem_condicoes_aceita_todas() {
+        assert!(Consulta::default().aceita(&pessoa("Ana", "ana@x.com", None)));
+        assert!(consulta(vec![Vec::new()]).aceita(&pessoa("Ana", "ana@x.com", None)));
+    }
+
+    #[test]
+    fn condicoes_de_cada_tipo() {
+        let ana = pessoa("Ana Souza", "Ana@Empresa.com", Some("52998224725"));
+
+        assert!(Condicao::Igual(Campo::Nome, "Ana Souza".into()).aceita(&ana));
+        assert!(!Condicao::Igual(Campo::Nome, "ana souza".into()).aceita(&ana));
+        assert!(Condicao::Contem(Campo::Email, "@empresa".into()).aceita(&ana));
+        assert!(Condicao::ComecaCom(Campo::Nome, "ana".into()).aceita(&ana));
+        assert!(!Condicao::ComecaCom(Campo::Nome, "souza".into()).aceita(&ana));
+        assert!(Condicao::Em(Campo::Documento, vec!["1".into(), "52998224725".into()]).aceita(&ana));
+
+        // Documento ausente vale ""
+        let sem_documento = pessoa("Bia", "bia@x.com", None);
+        assert!(Condicao::Igual(Campo::Documento, String::new()).aceita(&sem_documento));
+    }
+
+    #[test]
+    fn and_dentro_do_grupo_e_or_entre_grupos() {
+        let pessoas = [
+            pessoa("Ana", "ana@empresa.com", None),
+            pessoa("Ana", "ana@gmail.com", None),
+            pessoa("Bia", "bia@gmail.com", Some("123")),
+        ];
+        let consulta = consulta(vec![
+            vec![
+                Condicao::ComecaCom(Campo::Nome, "ana".into()),
+                Condicao::Contem(Campo::Email, "@empresa".into()),
+            ],
+            vec![Condicao::Igual(Campo::Documento, "123".into())],
+        ]);
+
+        let aceitas: Vec<&Pessoa> = pessoas.iter().filter(|p| consulta.aceita(p)).collect();
+        assert_eq!(aceitas.len(), 2);
+        assert_eq!(aceitas[0].email, "ana@empresa.com");
+        assert_eq!(aceitas[1].nome, "Bia");
+    }
+
+    #[test]
+    fn offset_e_limite_depois_do_filtro() {
+        let pessoas: Vec<Pessoa> = ["Ana", "Bia", "Abel", "Cid", "Alice", "Aldo"]
+            .iter()
+            .map(|nome| pessoa(nome, "x@x.com", None))
+            .collect();
+        let lidas = Cell::new(0);
+        let fluxo = pessoas.iter().cloned().inspect(|_| lidas.set(lidas.get() + 1)).map(Ok);
+
+        let mut consulta = consulta(vec![vec![Condicao::ComecaCom(Campo::Nome, "a".into())]]);
+        consulta.offset = 1;
+        consulta.limite = Some(2);
+
+        let resultado = consulta.aplicar(fluxo).unwrap();
+        assert_eq!(nomes(&resultado), ["Abel", "Alice"]);
+        // Para de ler ao atingir o limite: "Aldo" não é lido
+        assert_eq!(lidas.get(), 5);
+    }
+
+    #[test]
+    fn limite_zero_nao_le_nada() {
+        let consulta = Consulta { limite: Some(0), ..Consulta::default() };
+
+        let fluxo = std::iter::from_fn(|| -> Option<PhpResult<Pessoa>> { panic!("não deveria ler") });
+        assert!(consulta.aplicar(fluxo).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,580 @@
+use std::io::{self, BufRead};
+use std::mem;
+
+use super::backend::{Entrada, Esquema, Formato, LinhaInvalida, VERSAO_ESQUEMA};
+use super::Pessoa;
+
+// ============================================================================
+// CSV (RFC 4180)
+// Leitura e escrita de registros CSV com suporte a aspas e quebras de linha
+// ============================================================================
+
+/// Colunas dos arquivos CSV de pessoas, na ordem em que são gravadas
+const COLUNAS: [&str; 7] = ["id", "nome", "email", "telefone", "deletado_em", "versao", "documento"];
+
+/// Quantidade de colunas obrigatórias no cabeçalho (as primeiras de `COLUNAS`)
+const COLUNAS_OBRIGATORIAS: usize = 4;
+
+/// Prefixo da linha que marca a versão do esquema, antes do cabeçalho
+const MARCADOR_ESQUEMA: &str = "#esquema=";
+
+/// Registro lido do arquivo CSV
+#[derive(Debug, Clone)]
+pub struct RegistroCsv {
+    /// Campos já sem as aspas de escape
+    pub campos: Vec<String>,
+
+    /// Linha física (começando em 1) onde o registro inicia
+    pub linha: usize,
+
+    /// Texto original do registro, sem a quebra de linha final
+    pub bruto: String,
+
+    /// Se o arquivo terminou com aspas abertas neste registro (escrita
+    /// interrompida): os campos vão até o fim do arquivo
+    pub aspas_abertas: bool,
+}
+
+/// Leitor de CSV que respeita campos entre aspas, aspas duplicadas
+/// e valores com quebra de linha (um registro pode ocupar várias linhas)
+pub struct LeitorCsv<R> {
+    leitor: R,
+    buffer: String,
+    linha: usize,
+}
+
+impl<R: BufRead> LeitorCsv<R> {
+    /// Cria um leitor a partir de qualquer `BufRead`
+    pub fn new(leitor: R) -> Self {
+        Self {
+            leitor,
+            buffer: String::new(),
+            linha: 0,
+        }
+    }
+
+    /// Lê o próximo registro, ignorando linhas em branco
+    /// Retorna `None` ao fim do arquivo; aspas não fechadas no fim do
+    /// arquivo não são erro de leitura, e sim um registro com `aspas_abertas`
+    fn ler_registro(&mut self) -> io::Result<Option<RegistroCsv>> {
+        loop {
+            let inicio = self.linha + 1;
+            let mut campos = Vec::new();
+            let mut campo = String::new();
+            let mut entre_aspas = false;
+            let mut teve_aspas = false;
+            let mut leu_algo = false;
+            let mut bruto = String::new();
+
+            loop {
+                self.buffer.clear();
+                let lidos = self.leitor.read_line(&mut self.buffer)?;
+
+                if lidos == 0 {
+                    if !leu_algo {
+                        return Ok(None);
+                    }
+                    break;
+                }
+
+                self.linha += 1;
+                leu_algo = true;
+                bruto.push_str(&self.buffer);
+
+                let mut chars = self.buffer.chars().peekable();
+                while let Some(c) = chars.next() {
+                    if entre_aspas {
+                        if c == '"' {
+                            // Aspas duplicadas representam uma aspa literal
+                            if chars.peek() == Some(&'"') {
+                                chars.next();
+                                campo.push('"');
+                            } else {
+                                entre_aspas = false;
+                            }
+                        } else {
+                            campo.push(c);
+                        }
+                        continue;
+                    }
+
+                    match c {
+                        '"' => {
+                            entre_aspas = true;
+                            teve_aspas = true;
+                        }
+                        ',' => campos.push(mem::take(&mut campo)),
+                        '\r' if matches!(chars.peek(), Some('\n') | None) => {}
+                        '\n' => {}
+                        _ => campo.push(c),
+                    }
+                }
+
+                // Registro só termina quando não há aspas abertas
+                if !entre_aspas {
+                    break;
+                }
+            }
+
+            if campos.is_empty() && campo.trim().is_empty() && !teve_aspas {
+                continue; // Linha em branco
+            }
+
+            campos.push(campo);
+            bruto.truncate(bruto.trim_end_matches(['\r', '\n']).len());
+            return Ok(Some(RegistroCsv {
+                campos,
+                linha: inicio,
+                bruto,
+                aspas_abertas: entre_aspas,
+            }));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LeitorCsv<R> {
+    type Item = io::Result<RegistroCsv>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ler_registro().transpose()
+    }
+}
+
+/// Escapa um campo para CSV (adiciona aspas se necessário)
+pub fn escapar_campo(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Monta uma linha CSV completa (com `\n` ao final) a partir dos campos
+pub fn formatar_linha<S: AsRef<str>>(campos: &[S]) -> String {
+    let mut linha = campos
+        .iter()
+        .map(|c| escapar_campo(c.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    linha.push('\n');
+    linha
+}
+
+/// Posição de cada coluna conhecida em um arquivo, lida do cabeçalho
+#[derive(Debug, Clone)]
+struct MapaColunas {
+    id: usize,
+    nome: usize,
+    email: usize,
+    telefone: usize,
+    deletado_em: Option<usize>,
+    versao: Option<usize>,
+    documento: Option<usize>,
+
+    /// Colunas do cabeçalho, normalizadas (sem espaços e em minúsculas)
+    colunas: Vec<String>,
+}
+
+impl MapaColunas {
+    /// Localiza as colunas pelo nome, em qualquer ordem
+    /// Colunas desconhecidas são ignoradas; faltar uma obrigatória ou
+    /// repetir uma conhecida é erro
+    fn de_cabecalho(registro: &RegistroCsv) -> io::Result<Self> {
+        let colunas: Vec<String> = registro.campos
+            .iter()
+            .map(|c| c.trim().to_lowercase())
+            .collect();
+
+        // Cabeçalhos antigos que são prefixo do atual podem ter linhas mais
+        // longas, acrescentadas antes da migração: valem as posições padrão
+        let prefixo_do_atual = colunas.iter().zip(COLUNAS).all(|(c, padrao)| c == padrao);
+
+        let mut posicoes = [None; COLUNAS.len()];
+        for (indice, nome) in COLUNAS.iter().enumerate() {
+            let mut achadas = colunas
+                .iter()
+                .enumerate()
+                .filter(|(_, coluna)| coluna == nome)
+                .map(|(posicao, _)| posicao);
+            posicoes[indice] = achadas.next();
+
+            if achadas.next().is_some() {
+                return Err(erro_cabecalho(registro, format!("coluna {} repetida", nome)));
+            }
+            if posicoes[indice].is_none() {
+                if indice < COLUNAS_OBRIGATORIAS {
+                    return Err(erro_cabecalho(registro, format!("coluna obrigatória {} ausente", nome)));
+                }
+                if prefixo_do_atual {
+                    posicoes[indice] = Some(indice);
+                }
+            }
+        }
+
+        let [id, nome, email, telefone, deletado_em, versao, documento] = posicoes;
+        Ok(Self {
+            // As obrigatórias foram conferidas acima
+            id: id.unwrap_or_default(),
+            nome: nome.unwrap_or_default(),
+            email: email.unwrap_or_default(),
+            telefone: telefone.unwrap_or_default(),
+            deletado_em,
+            versao,
+            documento,
+            colunas,
+        })
+    }
+
+    /// Colunas do cabeçalho que não pertencem ao esquema
+    fn extras(&self) -> Vec<String> {
+        self.colunas
+            .iter()
+            .filter(|c| !COLUNAS.contains(&c.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Versão do esquema deduzida das colunas (arquivos sem marcador)
+    fn versao_deduzida(&self) -> i64 {
+        let tem = |nome: &str| self.colunas.iter().any(|c| c == nome);
+
+        if tem("documento") {
+            4
+        } else if tem("versao") {
+            3
+        } else if tem("deletado_em") {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// Início de um arquivo CSV: marcador de versão (opcional) e cabeçalho
+#[derive(Debug)]
+struct Preambulo {
+    /// Versão declarada pelo marcador (None em arquivos antigos)
+    versao: Option<i64>,
+
+    /// Colunas do cabeçalho
+    mapa: MapaColunas,
+}
+
+/// Lê o marcador de versão e o cabeçalho; None se o arquivo estiver vazio
+fn ler_preambulo<R: BufRead>(registros: &mut LeitorCsv<R>) -> io::Result<Option<Preambulo>> {
+    let Some(mut registro) = registros.next().transpose()? else {
+        return Ok(None);
+    };
+
+    let mut versao = None;
+    if let [campo] = registro.campos.as_slice() {
+        if campo.starts_with('#') {
+            let numero = campo
+                .strip_prefix(MARCADOR_ESQUEMA)
+                .and_then(|n| n.trim().parse::<i64>().ok())
+                .ok_or_else(|| erro_cabecalho(&registro, format!("marcador inválido: {}", campo)))?;
+
+            if numero > VERSAO_ESQUEMA {
+                return Err(erro_cabecalho(&registro, format!(
+                    "esquema {} é mais novo que o suportado ({})",
+                    numero, VERSAO_ESQUEMA
+                )));
+            }
+            versao = Some(numero);
+
+            registro = registros.next().transpose()?.ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Cabeçalho ausente depois do marcador de esquema",
+            ))?;
+        }
+    }
+
+    if registro.aspas_abertas {
+        return Err(erro_cabecalho(&registro, "aspas não fechadas".into()));
+    }
+
+    Ok(Some(Preambulo {
+        versao,
+        mapa: MapaColunas::de_cabecalho(&registro)?,
+    }))
+}
+
+/// Erro de cabeçalho com a linha onde ele aparece
+fn erro_cabecalho(registro: &RegistroCsv, motivo: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Cabeçalho inválido na linha {}: {}", registro.linha, motivo),
+    )
+}
+
+/// Formato CSV de pessoas: `id,nome,email,telefone,deletado_em,versao,documento`,
+/// precedido pelo marcador `#esquema=N`
+///
+/// As colunas são localizadas pelo cabeçalho, então arquivos com colunas
+/// reordenadas ou extras podem ser lidos; acréscimos, porém, são gravados
+/// na ordem padrão, por isso o backend migra o arquivo antes de escrever.
+/// Uma remoção é gravada como uma linha só com o ID e os demais campos
+/// vazios (`5,,,,,,`). Registros sempre gravam um número em `versao`,
+/// então nenhum tem essa forma, mesmo com nome, email e telefone vazios
+/// (permitidos por `RegrasValidacao`). Arquivos antigos, sem
+/// `deletado_em`, `versao` ou `documento`, continuam válidos (versão 0,
+/// sem documento); neles só há remoções com todos os campos vazios.
+#[derive(Debug, Clone, Copy)]
+pub struct FormatoCsv;
+
+impl Formato for FormatoCsv {
+    fn cabecalho(&self) -> String {
+        format!("{}{}\n{}\n", MARCADOR_ESQUEMA, VERSAO_ESQUEMA, COLUNAS.join(","))
+    }
+
+    fn codificar(&self, pessoa: &Pessoa) -> String {
+        formatar_linha(&[
+            pessoa.id.unwrap_or(0).to_string(),
+            pessoa.nome.clone(),
+            pessoa.email.clone(),
+            pessoa.telefone.clone(),
+            pessoa.deletado_em.map(|t| t.to_string()).unwrap_or_default(),
+            pessoa.versao.to_string(),
+            pessoa.documento.clone().unwrap_or_default(),
+        ])
+    }
+
+    fn codificar_remocao(&self, id: i64) -> String {
+        formatar_linha(&[id.to_string().as_str(), "", "", "", "", "", ""])
+    }
+
+    fn ler(&self, leitor: Box<dyn BufRead>) -> Box<dyn Iterator<Item = io::Result<Entrada>>> {
+        let mut registros = LeitorCsv::new(leitor);
+
+        let mapa = match ler_preambulo(&mut registros) {
+            Ok(Some(preambulo)) => preambulo.mapa,
+            Ok(None) => return Box::new(std::iter::empty()),
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+
+        Box::new(registros.map(move |registro| Ok(entrada_de_registro(&mapa, registro?))))
+    }
+
+    fn esquema(&self, leitor: Box<dyn BufRead>) -> io::Result<Esquema> {
+        let Some(preambulo) = ler_preambulo(&mut LeitorCsv::new(leitor))? else {
+            // Arquivo vazio: ganha marcador e cabeçalho na primeira escrita
+            return Ok(Esquema { versao: 0, atual: false, colunas_extras: Vec::new() });
+        };
+        let mapa = &preambulo.mapa;
+
+        Ok(Esquema {
+            versao: preambulo.versao.unwrap_or_else(|| mapa.versao_deduzida()),
+            atual: preambulo.versao == Some(VERSAO_ESQUEMA) && mapa.colunas == COLUNAS,
+            colunas_extras: mapa.extras(),
+        })
+    }
+}
+
+/// Interpreta um registro CSV como nova versão, remoção ou registro inválido
+fn entrada_de_registro(mapa: &MapaColunas, registro: RegistroCsv) -> Entrada {
+    // Registro cortado no meio: os campos não são confiáveis
+    if registro.aspas_abertas {
+        return Entrada::Invalida(LinhaInvalida {
+            linha: registro.linha,
+            conteudo: registro.bruto,
+            motivo: "aspas não fechadas até o fim do arquivo".into(),
+            aproveitavel: None,
+        });
+    }
+
+    let mut problema = None;
+    let pessoa = pessoa_de_campos(mapa, &registro.campos, &mut problema);
+
+    // Sem a coluna (arquivos antigos) ou com ela vazia: só uma remoção
+    // deixa `versao` sem número
+    let sem_versao = mapa
+        .versao
+        .and_then(|posicao| registro.campos.get(posicao))
+        .is_none_or(String::is_empty);
+
+    let (motivo, aproveitavel) = match (pessoa, problema) {
+        (Ok(pessoa), None) => {
+            return match pessoa.id {
+                Some(id)
+                    if sem_versao
+                        && pessoa.nome.is_empty()
+                        && pessoa.email.is_empty()
+                        && pessoa.telefone.is_empty()
+                        && pessoa.deletado_em.is_none()
+                        && pessoa.documento.is_none() =>
+                {
+                    Entrada::Remocao(id)
+                }
+                _ => Entrada::Registro(pessoa),
+            };
+        }
+        (Ok(pessoa), Some(motivo)) => (motivo, Some(pessoa)),
+        (Err(motivo), _) => (motivo, None),
+    };
+
+    Entrada::Invalida(LinhaInvalida {
+        linha: registro.linha,
+        conteudo: registro.bruto,
+        motivo,
+        aproveitavel,
+    })
+}
+
+/// Monta uma pessoa a partir dos campos de um registro CSV, nas posições
+/// indicadas pelo cabeçalho (`deletado_em`, `versao` e `documento` são opcionais)
+/// Sem uma coluna obrigatória não há o que aproveitar (Err); valores que
+/// não são números viram ausentes, anotando o problema em `problema`
+fn pessoa_de_campos(
+    mapa: &MapaColunas,
+    campos: &[String],
+    problema: &mut Option<String>,
+) -> Result<Pessoa, String> {
+    let obrigatorio = |posicao: usize, nome: &str| {
+        campos
+            .get(posicao)
+            .cloned()
+            .ok_or_else(|| format!("coluna {} ausente ({} campos)", nome, campos.len()))
+    };
+
+    let texto_id = obrigatorio(mapa.id, "id")?;
+    let id = texto_id.parse::<i64>().ok();
+    if id.is_none() {
+        *problema = Some(format!("ID inválido: \"{}\"", texto_id));
+    }
+
+    Ok(Pessoa {
+        id,
+        nome: obrigatorio(mapa.nome, "nome")?,
+        email: obrigatorio(mapa.email, "email")?,
+        telefone: obrigatorio(mapa.telefone, "telefone")?,
+        deletado_em: numero_opcional(campos, mapa.deletado_em, "deletado_em", problema),
+        versao: numero_opcional(campos, mapa.versao, "versao", problema).unwrap_or(0),
+        documento: mapa
+            .documento
+            .and_then(|posicao| campos.get(posicao))
+            .filter(|documento| !documento.is_empty())
+            .cloned(),
+    })
+}
+
+/// Lê uma coluna numérica opcional; vazia é ausente, e um valor que não é
+/// número também, anotando o problema (se ainda não houver outro)
+fn numero_opcional(
+    campos: &[String],
+    posicao: Option<usize>,
+    nome: &str,
+    problema: &mut Option<String>,
+) -> Option<i64> {
+    let texto = campos.get(posicao?)?;
+    if texto.is_empty() {
+        return None;
+    }
+
+    let numero = texto.parse::<i64>().ok();
+    if numero.is_none() {
+        problema.get_or_insert_with(|| format!("{} inválido: \"{}\"", nome, texto));
+    }
+    numero
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Registros de um texto CSV, na ordem lida
+    fn registros(texto: &str) -> Vec<RegistroCsv> {
+        LeitorCsv::new(texto.as_bytes())
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    /// Entradas de um arquivo de pessoas no esquema atual
+    fn entradas(linhas: &str) -> Vec<Entrada> {
+        let texto = format!("{}{}", FormatoCsv.cabecalho(), linhas);
+        FormatoCsv
+            .ler(Box::new(io::Cursor::new(texto)))
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn campos_entre_aspas() {
+        let lidos = registros("1,\"Silva, Ana\",\"diz \"\"oi\"\"\",\"\"\n");
+        assert_eq!(lidos.len(), 1);
+        assert_eq!(lidos[0].campos, ["1", "Silva, Ana", "diz \"oi\"", ""]);
+    }
+
+    #[test]
+    fn quebra_de_linha_dentro_de_aspas() {
+        let lidos = registros("1,\"linha 1\r\nlinha 2\",x\r\n\r\n2,b,y\n");
+        assert_eq!(lidos.len(), 2);
+        assert_eq!(lidos[0].campos, ["1", "linha 1\r\nlinha 2", "x"]);
+        assert_eq!(lidos[0].bruto, "1,\"linha 1\r\nlinha 2\",x");
+        assert_eq!(lidos[1].linha, 4);
+        assert_eq!(lidos[1].campos, ["2", "b", "y"]);
+    }
+
+    #[test]
+    fn aspas_abertas_no_fim_do_arquivo() {
+        let lidos = registros("1,a\n2,\"b\nc");
+        assert_eq!(lidos.len(), 2);
+        assert!(!lidos[0].aspas_abertas);
+        assert!(lidos[1].aspas_abertas);
+        assert_eq!(lidos[1].linha, 2);
+    }
+
+    #[test]
+    fn registro_cortado_vira_linha_invalida() {
+        let lidas = entradas("1,Ana,ana@x.com,1,,1,\n2,\"Bia");
+        assert!(matches!(&lidas[0], Entrada::Registro(p) if p.nome == "Ana"));
+        assert!(matches!(&lidas[1], Entrada::Invalida(i) if i.linha == 4 && i.aproveitavel.is_none()));
+    }
+
+    #[test]
+    fn escrita_e_leitura_preservam_os_campos() {
+        let pessoa = Pessoa {
+            id: Some(7),
+            nome: "Ana \"Aninha\", da Silva\nSegunda linha".into(),
+            email: "ana@x.com".into(),
+            telefone: "11 98765-4321".into(),
+            versao: 3,
+            documento: Some("11144477735".into()),
+            ..Pessoa::default()
+        };
+        let lidas = entradas(&FormatoCsv.codificar(&pessoa));
+        let [Entrada::Registro(lida)] = lidas.as_slice() else {
+            panic!("esperado um registro: {:?}", lidas);
+        };
+        assert_eq!(lida.id, Some(7));
+        assert_eq!(lida.nome, pessoa.nome);
+        assert_eq!(lida.versao, 3);
+        assert_eq!(lida.documento, pessoa.documento);
+    }
+
+    #[test]
+    fn remocao_so_sem_versao() {
+        let lidas = entradas(&FormatoCsv.codificar_remocao(5));
+        assert!(matches!(lidas.as_slice(), [Entrada::Remocao(5)]));
+
+        // Registro com nome, email e telefone vazios não é uma remoção
+        let vazio = Pessoa { id: Some(6), versao: 1, ..Pessoa::default() };
+        let lidas = entradas(&FormatoCsv.codificar(&vazio));
+        assert!(matches!(lidas.as_slice(), [Entrada::Registro(p)] if p.id == Some(6)));
+    }
+
+    #[test]
+    fn remocao_em_arquivo_antigo() {
+        let texto = "id,nome,email,telefone\n1,Ana,ana@x.com,1\n1,,,\n";
+        let lidas: Vec<Entrada> = FormatoCsv
+            .ler(Box::new(io::Cursor::new(texto)))
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert!(matches!(lidas.as_slice(), [Entrada::Registro(_), Entrada::Remocao(1)]));
+    }
+
+    #[test]
+    fn cabecalho_com_aspas_abertas_e_erro() {
+        let mut leitor = LeitorCsv::new("#esquema=4\n\"id,nome".as_bytes());
+        assert!(ler_preambulo(&mut leitor).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+// ============================================================================
+// DOCUMENTOS BRASILEIROS
+// Validação e formatação de CPF e CNPJ (inclusive o CNPJ alfanumérico)
+// ============================================================================
+
+/// Documento fiscal brasileiro, guardado sem pontuação
+///
+/// O CNPJ alfanumérico (Receita Federal, a partir de julho de 2026) tem
+/// 12 caracteres entre dígitos e letras maiúsculas seguidos de 2 dígitos
+/// verificadores; o CNPJ só numérico é um caso particular dele
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Documento {
+    /// CPF: 11 dígitos
+    Cpf(String),
+
+    /// CNPJ: 14 caracteres
+    Cnpj(String),
+}
+
+impl Documento {
+    /// Analisa um CPF ou CNPJ, com ou sem pontuação; o tipo é deduzido
+    /// pela quantidade de caracteres
+    pub fn analisar(texto: &str) -> Result<Self, String> {
+        let valor = sem_pontuacao(texto)?;
+        match valor.len() {
+            11 => Self::cpf(&valor),
+            14 => Self::cnpj(&valor),
+            0 => Err("documento vazio".into()),
+            n => Err(format!("informe 11 caracteres (CPF) ou 14 (CNPJ), não {}", n)),
+        }
+    }
+
+    /// Analisa um CPF, com ou sem pontuação
+    pub fn analisar_cpf(texto: &str) -> Result<Self, String> {
+        let valor = sem_pontuacao(texto)?;
+        if valor.len() != 11 {
+            return Err(format!("CPF deve ter 11 dígitos, não {}", valor.len()));
+        }
+        Self::cpf(&valor)
+    }
+
+    /// Analisa um CNPJ numérico ou alfanumérico, com ou sem pontuação
+    pub fn analisar_cnpj(texto: &str) -> Result<Self, String> {
+        let valor = sem_pontuacao(texto)?;
+        if valor.len() != 14 {
+            return Err(format!("CNPJ deve ter 14 caracteres, não {}", valor.len()));
+        }
+        Self::cnpj(&valor)
+    }
+
+    /// Valor sem pontuação, como é gravado
+    pub fn valor(&self) -> &str {
+        match self {
+            Self::Cpf(valor) | Self::Cnpj(valor) => valor,
+        }
+    }
+
+    /// Valor com a pontuação oficial: `111.444.777-35` ou `12.ABC.345/01DE-35`
+    pub fn formatado(&self) -> String {
+        let v = self.valor();
+        match self {
+            Self::Cpf(_) => format!("{}.{}.{}-{}", &v[..3], &v[3..6], &v[6..9], &v[9..]),
+            Self::Cnpj(_) => format!("{}.{}.{}/{}-{}", &v[..2], &v[2..5], &v[5..8], &v[8..12], &v[12..]),
+        }
+    }
+
+    /// Valor formatado com parte dos caracteres ocultos, para exibição:
+    /// `***.444.777-**` (padrão usado em documentos públicos) ou
+    /// `**.ABC.345/****-**` (oculta o início, a filial e os verificadores)
+    pub fn mascarado(&self) -> String {
+        let v = self.valor();
+        match self {
+            Self::Cpf(_) => format!("***.{}.{}-**", &v[3..6], &v[6..9]),
+            Self::Cnpj(_) => format!("**.{}.{}/****-**", &v[2..5], &v[5..8]),
+        }
+    }
+
+    /// Confere os dígitos verificadores de um CPF já sem pontuação
+    fn cpf(valor: &str) -> Result<Self, String> {
+        if !valor.bytes().all(|b| b.is_ascii_digit()) {
+            return Err("CPF deve ter apenas dígitos".into());
+        }
+        if repetido(valor) {
+            return Err("CPF com todos os dígitos iguais".into());
+        }
+
+        let valores: Vec<u32> = valor.bytes().map(|b| (b - b'0') as u32).collect();
+        let primeiro = digito_cpf(&valores[..9]);
+        let segundo = digito_cpf(&valores[..10]);
+        if [primeiro, segundo] != valores[9..] {
+            return Err("dígitos verificadores do CPF não conferem".into());
+        }
+
+        Ok(Self::Cpf(valor.to_string()))
+    }
+
+    /// Confere os dígitos verificadores de um CNPJ já sem pontuação
+    fn cnpj(valor: &str) -> Result<Self, String> {
+        let (raiz, verificadores) = valor.split_at(12);
+        if !raiz.bytes().all(|b| b.is_ascii_digit() || b.is_ascii_uppercase()) {
+            return Err("CNPJ deve ter apenas dígitos e letras nas 12 primeiras posições".into());
+        }
+        if !verificadores.bytes().all(|b| b.is_ascii_digit()) {
+            return Err("os dois últimos caracteres do CNPJ devem ser dígitos".into());
+        }
+        if repetido(valor) {
+            return Err("CNPJ com todos os caracteres iguais".into());
+        }
+
+        // Cada caractere vale seu código ASCII menos 48: dígitos valem
+        // eles mesmos e letras vão de 17 (A) a 42 (Z)
+        let valores: Vec<u32> = valor.bytes().map(|b| (b - b'0') as u32).collect();
+        let primeiro = digito_cnpj(&valores[..12]);
+        let segundo = digito_cnpj(&valores[..13]);
+        if [primeiro, segundo] != valores[12..] {
+            return Err("dígitos verificadores do CNPJ não conferem".into());
+        }
+
+        Ok(Self::Cnpj(valor.to_string()))
+    }
+}
+
+/// Remove a pontuação usual (ponto, barra, hífen e espaços) e passa as
+/// letras para maiúsculas; recusa qualquer outro caractere
+fn sem_pontuacao(texto: &str) -> Result<String, String> {
+    let mut valor = String::with_capacity(texto.len());
+    for c in texto.chars() {
+        match c {
+            '.' | '/' | '-' | ' ' => {}
+            c if c.is_ascii_alphanumeric() => valor.push(c.to_ascii_uppercase()),
+            c => return Err(format!("caractere inválido: {:?}", c)),
+        }
+    }
+    Ok(valor)
+}
+
+/// Se todos os caracteres são iguais (sequências que passam no cálculo
+/// dos verificadores mas não são documentos válidos)
+fn repetido(valor: &str) -> bool {
+    valor.bytes().all(|b| b == valor.as_bytes()[0])
+}
+
+/// Dígito verificador do CPF: pesos decrescentes a partir de n + 1
+fn digito_cpf(valores: &[u32]) -> u32 {
+    let peso_inicial = valores.len() as u32 + 1;
+    let soma: u32 = valores
+        .iter()
+        .enumerate()
+        .map(|(i, v)| v * (peso_inicial - i as u32))
+        .sum();
+    (soma * 10) % 11 % 10
+}
+
+/// Dígito verificador do CNPJ: pesos de 2 a 9 da direita para a esquerda
+fn digito_cnpj(valores: &[u32]) -> u32 {
+    let soma: u32 = valores
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, v)| v * (2 + i as u32 % 8))
+        .sum();
+    match soma % 11 {
+        0 | 1 => 0,
+        resto => 11 - resto,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpf() {
+        let cpf = Documento::analisar("111.444.777-35").unwrap();
+        assert_eq!(cpf, Documento::Cpf("11144477735".into()));
+        assert_eq!(cpf.formatado(), "111.444.777-35");
+        assert_eq!(cpf.mascarado(), "***.444.777-**");
+
+        assert!(Documento::analisar_cpf("111.444.777-36").is_err());
+        assert!(Documento::analisar_cpf("111.111.111-11").is_err());
+        assert!(Documento::analisar_cpf("11.222.333/0001-81").is_err());
+    }
+
+    #[test]
+    fn cnpj_numerico() {
+        let cnpj = Documento::analisar("11.222.333/0001-81").unwrap();
+        assert_eq!(cnpj, Documento::Cnpj("11222333000181".into()));
+        assert_eq!(cnpj.formatado(), "11.222.333/0001-81");
+        assert_eq!(cnpj.mascarado(), "**.222.333/****-**");
+
+        assert!(Documento::analisar_cnpj("11.222.333/0001-82").is_err());
+        assert!(Documento::analisar_cnpj("00.000.000/0000-00").is_err());
+    }
+
+    #[test]
+    fn cnpj_alfanumerico() {
+        // Exemplo da Receita Federal para o CNPJ alfanumérico
+        let cnpj = Documento::analisar("12.abc.345/01de-35").unwrap();
+        assert_eq!(cnpj.valor(), "12ABC34501DE35");
+        assert_eq!(cnpj.formatado(), "12.ABC.345/01DE-35");
+
+        assert!(Documento::analisar_cnpj("12.ABC.345/01DE-36").is_err());
+        assert!(Documento::analisar_cnpj("12.ABC.345/01DE-3A").is_err());
+    }
+
+    #[test]
+    fn digitos_do_cnpj_alfanumerico() {
+        // Letras valem o código ASCII menos 48 (A = 17)
+        let valores: Vec<u32> = "12ABC34501DE".bytes().map(|b| (b - b'0') as u32).collect();
+        assert_eq!(valores[2], 17);
+        assert_eq!(digito_cnpj(&valores), 3);
+        let mut com_primeiro = valores.clone();
+        com_primeiro.push(3);
+        assert_eq!(digito_cnpj(&com_primeiro), 5);
+    }
+
+    #[test]
+    fn entradas_invalidas() {
+        assert!(Documento::analisar("").is_err());
+        assert!(Documento::analisar("123").is_err());
+        assert!(Documento::analisar("111.444.777_35").is_err());
+        assert!(Documento::analisar_cpf("1114447773A").is_err());
+    }
+}
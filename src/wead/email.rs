@@ -0,0 +1,239 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+// ============================================================================
+// ENDEREÇOS DE EMAIL
+// Análise de endereços segundo a RFC 5322 (sintaxe) e a RFC 5321 (limites)
+// ============================================================================
+
+/// Tamanho máximo da parte local, em bytes (RFC 5321, 4.5.3.1.1)
+const TAMANHO_MAXIMO_LOCAL: usize = 64;
+
+/// Tamanho máximo do domínio em ASCII (RFC 1035, sem o ponto final)
+const TAMANHO_MAXIMO_DOMINIO: usize = 253;
+
+/// Tamanho máximo de um rótulo do domínio (RFC 1035)
+const TAMANHO_MAXIMO_ROTULO: usize = 63;
+
+/// Tamanho máximo do endereço inteiro: o caminho da RFC 5321 (256) sem
+/// os sinais `<` e `>`
+const TAMANHO_MAXIMO: usize = 254;
+
+/// Endereço de email analisado e normalizado
+///
+/// Aceita o `addr-spec` da RFC 5322 sem comentários nem espaços dobrados
+/// (formas obsoletas que não fazem sentido num cadastro):
+/// - parte local em dot-atom (`ana.silva+tag`) ou entre aspas (`"ana silva"`)
+/// - domínio com rótulos LDH, inclusive internacionalizados (`café.com.br`,
+///   convertido para punycode), ou literal de endereço (`[192.0.2.1]`,
+///   `[IPv6:2001:db8::1]`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnderecoEmail {
+    /// Parte local, como recebida (a RFC a trata como sensível à caixa)
+    pub local: String,
+
+    /// Domínio em ASCII e minúsculas (punycode para domínios internacionais)
+    pub dominio: String,
+
+    /// Domínio em Unicode e minúsculas, para exibição
+    pub dominio_unicode: String,
+}
+
+impl EnderecoEmail {
+    /// Analisa um endereço; o erro descreve o primeiro problema encontrado
+    pub fn analisar(email: &str) -> Result<Self, String> {
+        if email.is_empty() {
+            return Err("endereço vazio".into());
+        }
+
+        // O último @ separa o domínio: uma parte local entre aspas pode ter @
+        let (local, dominio) = email
+            .rsplit_once('@')
+            .ok_or_else(|| "falta o @ entre a parte local e o domínio".to_string())?;
+
+        verificar_local(local)?;
+        let (dominio, dominio_unicode) = normalizar_dominio(dominio)?;
+
+        if local.len() + 1 + dominio.len() > TAMANHO_MAXIMO {
+            return Err(format!("endereço com mais de {} caracteres", TAMANHO_MAXIMO));
+        }
+
+        Ok(Self {
+            local: local.to_string(),
+            dominio,
+            dominio_unicode,
+        })
+    }
+
+    /// Endereço normalizado: parte local intacta e domínio em ASCII minúsculo
+    pub fn normalizado(&self) -> String {
+        format!("{}@{}", self.local, self.dominio)
+    }
+}
+
+/// Caracteres `atext` da RFC 5322, usados nos átomos da parte local
+fn atext(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c)
+}
+
+/// Verifica a parte local (dot-atom ou quoted-string)
+fn verificar_local(local: &str) -> Result<(), String> {
+    if local.is_empty() {
+        return Err("parte local vazia".into());
+    }
+    if local.len() > TAMANHO_MAXIMO_LOCAL {
+        return Err(format!("parte local com mais de {} caracteres", TAMANHO_MAXIMO_LOCAL));
+    }
+    if !local.is_ascii() {
+        return Err("a parte local só pode ter caracteres ASCII".into());
+    }
+
+    if let Some(conteudo) = local.strip_prefix('"') {
+        let conteudo = conteudo
+            .strip_suffix('"')
+            .ok_or_else(|| "aspas da parte local não fechadas".to_string())?;
+        return verificar_entre_aspas(conteudo);
+    }
+
+    if local.split('.').any(str::is_empty) {
+        return Err("a parte local não pode começar ou terminar com ponto nem ter pontos seguidos".into());
+    }
+    match local.chars().find(|&c| c != '.' && !atext(c)) {
+        Some(c) => Err(format!("caractere inválido na parte local: {:?}", c)),
+        None => Ok(()),
+    }
+}
+
+/// Verifica o conteúdo de uma parte local entre aspas: `qtext`, espaços
+/// e pares com barra invertida (`\"`, `\\`)
+fn verificar_entre_aspas(conteudo: &str) -> Result<(), String> {
+    let mut caracteres = conteudo.chars();
+    while let Some(c) = caracteres.next() {
+        match c {
+            '\\' => match caracteres.next() {
+                Some(escapado) if escapado == ' ' || escapado == '\t' || escapado.is_ascii_graphic() => {}
+                _ => return Err("barra invertida sem caractere válido a seguir na parte local".into()),
+            },
+            '"' => return Err("aspas dentro da parte local precisam de barra invertida".into()),
+            ' ' | '\t' => {}
+            c if c.is_ascii_graphic() => {}
+            c => return Err(format!("caractere inválido na parte local: {:?}", c)),
+        }
+    }
+    Ok(())
+}
+
+/// Normaliza o domínio; retorna as formas ASCII e Unicode
+fn normalizar_dominio(dominio: &str) -> Result<(String, String), String> {
+    if dominio.is_empty() {
+        return Err("domínio vazio".into());
+    }
+
+    if let Some(literal) = dominio.strip_prefix('[') {
+        let literal = literal
+            .strip_suffix(']')
+            .ok_or_else(|| "colchetes do domínio não fechados".to_string())?;
+        let literal = normalizar_literal(literal)?;
+        return Ok((literal.clone(), literal));
+    }
+
+    if dominio.split('.').any(str::is_empty) {
+        return Err("o domínio não pode começar ou terminar com ponto nem ter pontos seguidos".into());
+    }
+
+    // UTS #46: minúsculas, normalização Unicode e punycode; as regras
+    // estritas também recusam caracteres fora de letras, dígitos e hífen
+    let ascii = idna::domain_to_ascii_strict(dominio)
+        .map_err(|_| format!("domínio inválido: {}", dominio))?;
+
+    let rotulos: Vec<&str> = ascii.split('.').collect();
+    if rotulos.iter().any(|rotulo| rotulo.len() > TAMANHO_MAXIMO_ROTULO) {
+        return Err(format!("parte do domínio com mais de {} caracteres", TAMANHO_MAXIMO_ROTULO));
+    }
+    if ascii.len() > TAMANHO_MAXIMO_DOMINIO {
+        return Err(format!("domínio com mais de {} caracteres", TAMANHO_MAXIMO_DOMINIO));
+    }
+    if rotulos.len() < 2 {
+        return Err("o domínio precisa de ao menos um ponto (ex.: exemplo.com)".into());
+    }
+    if rotulos.last().is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit())) {
+        return Err("o domínio não pode terminar em parte só com números".into());
+    }
+
+    let (unicode, _) = idna::domain_to_unicode(&ascii);
+    Ok((ascii, unicode))
+}
+
+/// Normaliza o conteúdo de um literal de endereço (`192.0.2.1` ou
+/// `IPv6:2001:db8::1`); retorna o literal com colchetes
+fn normalizar_literal(literal: &str) -> Result<String, String> {
+    let ipv6 = literal
+        .get(..5)
+        .filter(|prefixo| prefixo.eq_ignore_ascii_case("IPv6:"))
+        .map(|_| &literal[5..]);
+
+    match ipv6 {
+        Some(endereco) => endereco
+            .parse::<Ipv6Addr>()
+            .map(|ip| format!("[IPv6:{}]", ip))
+            .map_err(|_| format!("endereço IPv6 inválido no domínio: {}", endereco)),
+        None => literal
+            .parse::<Ipv4Addr>()
+            .map(|ip| format!("[{}]", ip))
+            .map_err(|_| format!("literal de endereço inválido no domínio: [{}]", literal)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dominio_internacional_vira_punycode() {
+        let email = EnderecoEmail::analisar("Ana@Café.COM.br").unwrap();
+        assert_eq!(email.local, "Ana");
+        assert_eq!(email.dominio, "xn--caf-dma.com.br");
+        assert_eq!(email.dominio_unicode, "café.com.br");
+        assert_eq!(email.normalizado(), "Ana@xn--caf-dma.com.br");
+    }
+
+    #[test]
+    fn dominio_ja_em_punycode() {
+        let email = EnderecoEmail::analisar("ana@XN--CAF-DMA.com.br").unwrap();
+        assert_eq!(email.dominio, "xn--caf-dma.com.br");
+        assert_eq!(email.dominio_unicode, "café.com.br");
+    }
+
+    #[test]
+    fn dominio_invalido() {
+        for email in ["ana@exemplo", "ana@.com", "ana@a..com", "ana@ex_emplo.com", "ana@exemplo.123", "ana@xn--zz.com"] {
+            assert!(EnderecoEmail::analisar(email).is_err(), "{}", email);
+        }
+    }
+
+    #[test]
+    fn parte_local() {
+        for email in ["ana.silva+tag@x.com", "\"ana silva\"@x.com", "\"a@b\"@x.com", "\"a\\\"b\"@x.com"] {
+            assert!(EnderecoEmail::analisar(email).is_ok(), "{}", email);
+        }
+        for email in [".ana@x.com", "ana..silva@x.com", "ana silva@x.com", "\"ana@x.com", "anã@x.com", "@x.com"] {
+            assert!(EnderecoEmail::analisar(email).is_err(), "{}", email);
+        }
+    }
+
+    #[test]
+    fn literais_de_endereco() {
+        let email = EnderecoEmail::analisar("ana@[IPV6:2001:DB8::1]").unwrap();
+        assert_eq!(email.dominio, "[IPv6:2001:db8::1]");
+        assert_eq!(EnderecoEmail::analisar("ana@[192.0.2.1]").unwrap().dominio, "[192.0.2.1]");
+        assert!(EnderecoEmail::analisar("ana@[300.0.0.1]").is_err());
+    }
+
+    #[test]
+    fn limites_de_tamanho() {
+        let local = "a".repeat(TAMANHO_MAXIMO_LOCAL);
+        assert!(EnderecoEmail::analisar(&format!("{}@x.com", local)).is_ok());
+        assert!(EnderecoEmail::analisar(&format!("a{}@x.com", local)).is_err());
+        let rotulo = "a".repeat(TAMANHO_MAXIMO_ROTULO + 1);
+        assert!(EnderecoEmail::analisar(&format!("ana@{}.com", rotulo)).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use ext_php_rs::prelude::*;
+use ext_php_rs::{
+    boxed::ZBox,
+    convert::IntoZval,
+    types::{ZendClassObject, ZendHashTable},
+    zend::ClassEntry,
+};
+use std::io;
+use std::path::Path;
+
+use super::validacao::Violacao;
+
+// ============================================================================
+// EXCEÇÃO: Wead\Exception
+// Base de todas as exceções lançadas pela extensão
+// ============================================================================
+
+/// Exceção base do namespace Wead; `getCode()` traz um dos códigos
+/// estáveis declarados como constantes desta classe
+/// Equivalente PHP:
+/// ```php
+/// try {
+///     $storage->atualizar($pessoa);
+/// } catch (Wead\NaoEncontradoException $e) {
+///     echo "Sem a pessoa ", $e->obterId();
+/// } catch (Wead\Exception $e) {
+///     if ($e->getCode() === Wead\Exception::CODIGO_CONFLITO) { /* recarregar */ }
+/// }
+/// ```
+#[php_class]
+#[php(name = "Wead\\Exception")]
+#[php(extends(ce = ce::exception, stub = "\\Exception"))]
+#[derive(Default)]
+pub struct WeadException;
+
+#[php_impl]
+impl WeadException {
+    /// Erro sem categoria mais específica
+    pub const CODIGO_GERAL: i32 = 1;
+
+    /// Operação incompatível com o estado da transação
+    pub const CODIGO_TRANSACAO: i32 = 2;
+
+    /// Dado ou parâmetro inválido (ValidacaoException)
+    pub const CODIGO_VALIDACAO: i32 = 100;
+
+    /// Pessoa inexistente (NaoEncontradoException)
+    pub const CODIGO_NAO_ENCONTRADO: i32 = 200;
+
+    /// Falha de leitura ou escrita no armazenamento (ArmazenamentoException)
+    pub const CODIGO_ARMAZENAMENTO: i32 = 300;
+
+    /// Conteúdo armazenado corrompido ou em esquema desconhecido
+    /// (ArmazenamentoException)
+    pub const CODIGO_DADOS_INVALIDOS: i32 = 301;
+
+    /// Bloqueio não obtido a tempo (BloqueioException)
+    pub const CODIGO_BLOQUEIO: i32 = 302;
+
+    /// Restrição de unicidade violada (DuplicidadeException)
+    pub const CODIGO_DUPLICIDADE: i32 = 400;
+
+    /// Pessoa alterada por outra operação (ConflitoException)
+    pub const CODIGO_CONFLITO: i32 = 401;
+}
+
+impl WeadException {
+    /// Erro genérico da extensão com o código informado
+    pub fn erro(codigo: i32, mensagem: String) -> PhpException {
+        da_classe::<WeadException>(mensagem, codigo)
+    }
+}
+
+/// Classe PHP `Wead\Exception`, mãe das demais exceções
+fn ce_wead_exception() -> &'static ClassEntry {
+    WeadException::get_metadata().ce()
+}
+
+// ============================================================================
+// EXCEÇÃO: ValidacaoException
+// Lançada quando um dado ou parâmetro é inválido
+// ============================================================================
+
+/// Exceção de validação; informa o campo (ou parâmetro) rejeitado e,
+/// na validação de uma pessoa, todas as regras violadas
+#[php_class]
+#[php(name = "Wead\\ValidacaoException")]
+#[php(extends(ce = ce_wead_exception, stub = "\\Wead\\Exception"))]
+#[derive(Default)]
+pub struct ValidacaoException {
+    /// Primeiro campo ou parâmetro inválido
+    campo: String,
+
+    /// Todas as regras violadas
+    erros: Vec<Violacao>,
+}
+
+#[php_impl]
+impl ValidacaoException {
+    /// Obtém o (primeiro) campo ou parâmetro inválido (ex.: email, offset)
+    /// @return string
+    pub fn obter_campo(&self) -> String {
+        self.campo.clone()
+    }
+
+    /// Obtém todas as regras violadas
+    /// @return array Lista de ['campo' => string, 'regra' => string, 'mensagem' => string]
+    pub fn obter_erros(&self) -> PhpResult<Vec<ZBox<ZendHashTable>>> {
+        self.erros.iter().map(Violacao::para_array).collect()
+    }
+}
+
+impl ValidacaoException {
+    /// Erro de validação de um único campo ou parâmetro
+    pub fn erro(campo: &str, mensagem: String) -> PhpException {
+        Self::de_violacoes(vec![Violacao::new(campo, "valor", mensagem)])
+    }
+
+    /// Erro com todas as violações encontradas (ao menos uma); a mensagem
+    /// junta as mensagens de cada uma
+    pub fn de_violacoes(erros: Vec<Violacao>) -> PhpException {
+        let mensagem = erros
+            .iter()
+            .map(|violacao| violacao.mensagem.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        let estado = Self {
+            campo: erros.first().map(|violacao| violacao.campo.clone()).unwrap_or_default(),
+            erros,
+        };
+        com_estado(estado, mensagem, WeadException::CODIGO_VALIDACAO)
+    }
+}
+
+// ============================================================================
+// EXCEÇÃO: NaoEncontradoException
+// Lançada quando a pessoa pedida não existe
+// ============================================================================
+
+/// Exceção de pessoa inexistente; informa o ID procurado
+#[php_class]
+#[php(name = "Wead\\NaoEncontradoException")]
+#[php(extends(ce = ce_wead_exception, stub = "\\Wead\\Exception"))]
+#[derive(Default)]
+pub struct NaoEncontradoException {
+    /// ID procurado
+    id: i64,
+}
+
+#[php_impl]
+impl NaoEncontradoException {
+    /// Obtém o ID que não foi encontrado
+    /// @return int
+    pub fn obter_id(&self) -> i64 {
+        self.id
+    }
+}
+
+impl NaoEncontradoException {
+    /// Erro de pessoa inexistente
+    pub fn erro(id: i64) -> PhpException {
+        com_estado(
+            Self { id },
+            format!("Pessoa com ID {} não encontrada", id),
+            WeadException::CODIGO_NAO_ENCONTRADO,
+        )
+    }
+}
+
+// ============================================================================
+// EXCEÇÃO: ArmazenamentoException
+// Lançada quando o armazenamento não pode ser lido ou gravado
+// ============================================================================
+
+/// Exceção de armazenamento; informa o caminho envolvido e, quando a
+/// falha vem do sistema operacional, o erro original
+#[php_class]
+#[php(name = "Wead\\ArmazenamentoException")]
+#[php(extends(ce = ce_wead_exception, stub = "\\Wead\\Exception"))]
+#[derive(Default)]
+pub struct ArmazenamentoException {
+    /// Arquivo ou banco envolvido (None se o backend não o informa)
+    caminho: Option<String>,
+
+    /// Descrição do erro do sistema operacional
+    erro_so: Option<String>,
+
+    /// Código do erro do sistema operacional (errno)
+    codigo_so: Option<i32>,
+}
+
+#[php_impl]
+impl ArmazenamentoException {
+    /// Obtém o arquivo ou banco envolvido
+    /// @return string|null
+    pub fn obter_caminho(&self) -> Option<String> {
+        self.caminho.clone()
+    }
+
+    /// Obtém a descrição do erro do sistema operacional
+    /// @return string|null null se a falha não veio do sistema (ex.: dados corrompidos)
+    pub fn obter_erro_so(&self) -> Option<String> {
+        self.erro_so.clone()
+    }
+
+    /// Obtém o código do erro do sistema operacional (errno)
+    /// @return int|null
+    pub fn obter_codigo_so(&self) -> Option<i32> {
+        self.codigo_so
+    }
+}
+
+impl ArmazenamentoException {
+    /// Falha de E/S em `caminho`; a mensagem é "`contexto`: `erro`"
+    pub fn io(caminho: &Path, contexto: &str, erro: &io::Error) -> PhpException {
+        let estado = Self {
+            caminho: Some(caminho.to_string_lossy().into_owned()),
+            erro_so: Some(erro.to_string()),
+            codigo_so: erro.raw_os_error(),
+        };
+        com_estado(
+            estado,
+            format!("{}: {}", contexto, erro),
+            WeadException::CODIGO_ARMAZENAMENTO,
+        )
+    }
+
+    /// Conteúdo de `caminho` que não pode ser interpretado
+    pub fn dados_invalidos(caminho: &Path, mensagem: String) -> PhpException {
+        let estado = Self {
+            caminho: Some(caminho.to_string_lossy().into_owned()),
+            ..Self::default()
+        };
+        com_estado(estado, mensagem, WeadException::CODIGO_DADOS_INVALIDOS)
+    }
+
+    /// Falha do armazenamento sem caminho conhecido (erros do SQLite)
+    #[cfg(feature = "sqlite")]
+    pub fn erro(codigo: i32, mensagem: String) -> PhpException {
+        com_estado(Self::default(), mensagem, codigo)
+    }
+}
+
+// ============================================================================
+// EXCEÇÃO: BloqueioException
+// Lançada quando o bloqueio do arquivo não é obtido a tempo
+// ============================================================================
+
+/// Exceção lançada quando o Storage não consegue bloquear o arquivo
+/// dentro do tempo limite configurado
+#[php_class]
+#[php(name = "Wead\\BloqueioException")]
+#[php(extends(ce = ce_wead_exception, stub = "\\Wead\\Exception"))]
+#[derive(Default)]
+pub struct BloqueioException;
+
+impl BloqueioException {
+    /// Erro de bloqueio não obtido
+    pub fn erro(mensagem: String) -> PhpException {
+        da_classe::<BloqueioException>(mensagem, WeadException::CODIGO_BLOQUEIO)
+    }
+}
+
+// ============================================================================
+// EXCEÇÃO: DuplicidadeException
+// Lançada quando uma restrição de unicidade é violada
+// ============================================================================
+
+/// Exceção lançada quando o email já pertence a outra pessoa (com a
+/// restrição de email único ativa) ou quando a chave do upsert é ambígua
+#[php_class]
+#[php(name = "Wead\\DuplicidadeException")]
+#[php(extends(ce = ce_wead_exception, stub = "\\Wead\\Exception"))]
+#[derive(Default)]
+pub struct DuplicidadeException;
+
+impl DuplicidadeException {
+    /// Erro de valor duplicado
+    pub fn erro(mensagem: String) -> PhpException {
+        da_classe::<DuplicidadeException>(mensagem, WeadException::CODIGO_DUPLICIDADE)
+    }
+}
+
+// ============================================================================
+// EXCEÇÃO: ConflitoException
+// Lançada quando a pessoa foi alterada desde que foi carregada
+// ============================================================================
+
+/// Exceção lançada por `Storage::atualizar` quando a versão da pessoa
+/// não é mais a armazenada (outra operação a alterou antes)
+#[php_class]
+#[php(name = "Wead\\ConflitoException")]
+#[php(extends(ce = ce_wead_exception, stub = "\\Wead\\Exception"))]
+#[derive(Default)]
+pub struct ConflitoException;
+
+impl ConflitoException {
+    /// Erro de versão desatualizada
+    pub fn erro(mensagem: String) -> PhpException {
+        da_classe::<ConflitoException>(mensagem, WeadException::CODIGO_CONFLITO)
+    }
+}
+
+/// Exceção da classe `T`, sem estado além de mensagem e código
+fn da_classe<T: RegisteredClass>(mensagem: String, codigo: i32) -> PhpException {
+    PhpException::new(mensagem, codigo, T::get_metadata().ce())
+}
+
+/// Exceção da classe `T` lançada já com o estado informado, para que os
+/// getters dela funcionem no objeto capturado pelo PHP
+fn com_estado<T: RegisteredClass>(estado: T, mensagem: String, codigo: i32) -> PhpException {
+    let objeto = ZendClassObject::new(estado);
+
+    // Mensagem e código são propriedades de \Exception, preenchidas pelo
+    // construtor herdado (as classes daqui não declaram construtor)
+    let construido = objeto
+        .std
+        .try_call_method("__construct", vec![&mensagem, &codigo])
+        .and_then(|_| objeto.into_zval(false));
+
+    match construido {
+        Ok(objeto) => PhpException::new(mensagem, codigo, T::get_metadata().ce()).with_object(objeto),
+        // Sem o objeto a exceção ainda sai da classe certa, só sem o estado
+        Err(_) => da_classe::<T>(mensagem, codigo),
+    }
+}
@@ -0,0 +1,251 @@
+use ext_php_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::backend::Operacao;
+use super::{agora, Pessoa};
+
+// ============================================================================
+// CLASSE: Alteracao
+// Entrada do histórico de alterações de uma pessoa
+// ============================================================================
+
+/// Alteração gravada por `Storage` a cada criação, atualização, exclusão,
+/// restauração ou expurgo de uma pessoa
+/// Equivalente PHP:
+/// ```php
+/// foreach ($storage->historico(5) as $alteracao) {
+///     echo $alteracao->obterOperacao(), ' por ', $alteracao->obterAtor() ?? '?';
+/// }
+/// ```
+#[php_class]
+#[php(name = "Wead\\Alteracao")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alteracao {
+    /// ID da pessoa alterada
+    pub id_pessoa: i64,
+
+    /// Operação: criar, atualizar, deletar, restaurar ou expurgar
+    pub operacao: String,
+
+    /// Valores antes da alteração (None na criação)
+    pub anterior: Option<Pessoa>,
+
+    /// Valores depois da alteração (None no expurgo)
+    pub nova: Option<Pessoa>,
+
+    /// Momento da alteração (timestamp Unix)
+    pub momento: i64,
+
+    /// Quem fez a alteração, conforme `Storage::definir_ator()`
+    pub ator: Option<String>,
+}
+
+#[php_impl]
+impl Alteracao {
+    /// Obtém o ID da pessoa alterada
+    /// @return int
+    pub fn obter_id_pessoa(&self) -> i64 {
+        self.id_pessoa
+    }
+
+    /// Obtém a operação realizada
+    /// @return string criar, atualizar, deletar, restaurar ou expurgar
+    pub fn obter_operacao(&self) -> String {
+        self.operacao.clone()
+    }
+
+    /// Obtém a pessoa como estava antes da alteração
+    /// @return Pessoa|null null na criação
+    pub fn obter_anterior(&self) -> Option<Pessoa> {
+        self.anterior.clone()
+    }
+
+    /// Obtém a pessoa como ficou depois da alteração
+    /// @return Pessoa|null null no expurgo
+    pub fn obter_nova(&self) -> Option<Pessoa> {
+        self.nova.clone()
+    }
+
+    /// Obtém o momento da alteração
+    /// @return int Timestamp Unix
+    pub fn obter_momento(&self) -> i64 {
+        self.momento
+    }
+
+    /// Obtém quem fez a alteração
+    /// @return string|null
+    pub fn obter_ator(&self) -> Option<String> {
+        self.ator.clone()
+    }
+}
+
+impl Alteracao {
+    /// Monta a alteração entre duas versões de uma pessoa, deduzindo a
+    /// operação pela mudança; None se não houver versão com ID
+    pub fn new(anterior: Option<Pessoa>, nova: Option<Pessoa>, ator: Option<String>) -> Option<Self> {
+        let id_pessoa = nova.as_ref().or(anterior.as_ref())?.id?;
+
+        let operacao = match (&anterior, &nova) {
+            (None, _) => "criar",
+            (Some(_), None) => "expurgar",
+            (Some(a), Some(n)) => match (a.deletado_em, n.deletado_em) {
+                (None, Some(_)) => "deletar",
+                (Some(_), None) => "restaurar",
+                _ => "atualizar",
+            },
+        };
+
+        Some(Self {
+            id_pessoa,
+            operacao: operacao.to_string(),
+            anterior,
+            nova,
+            momento: agora(),
+            ator,
+        })
+    }
+}
+
+/// Alterações produzidas por uma sequência de operações aplicada sobre
+/// `pessoas` (o estado anterior, lido sob o mesmo bloqueio da gravação);
+/// `Limpar` expurga cada pessoa existente, em ordem de ID
+pub fn alteracoes_das_operacoes(
+    pessoas: &[Pessoa],
+    operacoes: &[Operacao],
+    ator: &Option<String>,
+) -> Vec<Alteracao> {
+    let mut estado: HashMap<i64, Pessoa> = pessoas
+        .iter()
+        .filter_map(|p| Some((p.id?, p.clone())))
+        .collect();
+    let mut alteracoes = Vec::with_capacity(operacoes.len());
+
+    for operacao in operacoes {
+        let (anterior, nova) = match operacao {
+            Operacao::Criar(pessoa) | Operacao::Atualizar(pessoa) => {
+                let Some(id) = pessoa.id else { continue };
+                (estado.insert(id, pessoa.clone()), Some(pessoa.clone()))
+            }
+            Operacao::Deletar(id) => (estado.remove(id), None),
+            Operacao::Limpar => {
+                let mut removidas: Vec<Pessoa> = estado.drain().map(|(_, p)| p).collect();
+                removidas.sort_by_key(|p| p.id);
+                alteracoes.extend(
+                    removidas.into_iter().filter_map(|p| Alteracao::new(Some(p), None, ator.clone()))
+                );
+                continue;
+            }
+        };
+
+        alteracoes.extend(Alteracao::new(anterior, nova, ator.clone()));
+    }
+
+    alteracoes
+}
+
+/// Pessoa como estava no momento informado, segundo seu histórico em
+/// ordem cronológica
+/// - Some(versão) se alguma alteração aconteceu até o momento (a versão é
+///   None se a pessoa ainda não existia ou já tinha sido expurgada)
+/// - None se o histórico não diz nada sobre o momento (sem alterações)
+pub fn estado_em(alteracoes: &[Alteracao], momento: i64) -> Option<Option<Pessoa>> {
+    if let Some(alteracao) = alteracoes.iter().rev().find(|a| a.momento <= momento) {
+        return Some(alteracao.nova.clone());
+    }
+
+    // Antes da primeira alteração registrada vale o valor anterior a ela
+    alteracoes.first().map(|alteracao| alteracao.anterior.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pessoa com ID e nome; os demais campos não importam aqui
+    fn pessoa(id: i64, nome: &str) -> Pessoa {
+        let mut pessoa = Pessoa::__construct(nome.into(), format!("{}@x.com", id), "1".into());
+        pessoa.definir_id(id);
+        pessoa
+    }
+
+    /// Alteração com momento fixo
+    fn alteracao(anterior: Option<Pessoa>, nova: Option<Pessoa>, momento: i64) -> Alteracao {
+        let mut alteracao = Alteracao::new(anterior, nova, None).unwrap();
+        alteracao.momento = momento;
+        alteracao
+    }
+
+    /// Operações das alterações, na ordem
+    fn operacoes(alteracoes: &[Alteracao]) -> Vec<&str> {
+        alteracoes.iter().map(|a| a.operacao.as_str()).collect()
+    }
+
+    #[test]
+    fn operacao_deduzida_pela_mudanca() {
+        let ativa = pessoa(1, "Ana");
+        let mut deletada = ativa.clone();
+        deletada.deletado_em = Some(10);
+
+        let alteracoes = [
+            alteracao(None, Some(ativa.clone()), 1),
+            alteracao(Some(ativa.clone()), Some(pessoa(1, "Ana Maria")), 2),
+            alteracao(Some(ativa.clone()), Some(deletada.clone()), 3),
+            alteracao(Some(deletada), Some(ativa.clone()), 4),
+            alteracao(Some(ativa), None, 5),
+        ];
+        assert_eq!(operacoes(&alteracoes), ["criar", "atualizar", "deletar", "restaurar", "expurgar"]);
+        assert!(Alteracao::new(None, Some(Pessoa::__construct("".into(), "".into(), "".into())), None).is_none());
+    }
+
+    #[test]
+    fn estado_em_cada_momento() {
+        let alteracoes = [
+            alteracao(None, Some(pessoa(1, "Ana")), 10),
+            alteracao(Some(pessoa(1, "Ana")), Some(pessoa(1, "Bia")), 20),
+            alteracao(Some(pessoa(1, "Bia")), None, 30),
+        ];
+        let nome = |momento| estado_em(&alteracoes, momento).map(|p| p.map(|p| p.nome));
+
+        assert_eq!(nome(5), Some(None));
+        assert_eq!(nome(10), Some(Some("Ana".into())));
+        assert_eq!(nome(25), Some(Some("Bia".into())));
+        assert_eq!(nome(30), Some(None));
+        assert!(estado_em(&[], 30).is_none());
+    }
+
+    #[test]
+    fn estado_antes_do_historico_vale_o_anterior() {
+        // Pessoa migrada já existente: o primeiro registro é uma atualização
+        let alteracoes = [alteracao(Some(pessoa(1, "Ana")), Some(pessoa(1, "Bia")), 20)];
+        assert_eq!(estado_em(&alteracoes, 10).unwrap().unwrap().nome, "Ana");
+    }
+
+    #[test]
+    fn alteracoes_de_uma_transacao() {
+        let pessoas = [pessoa(1, "Ana"), pessoa(2, "Bia")];
+        let operacoes_transacao = [
+            Operacao::Atualizar(pessoa(1, "Ana Maria")),
+            Operacao::Criar(pessoa(3, "Cid")),
+            Operacao::Deletar(2),
+            Operacao::Atualizar(pessoa(3, "Cida")),
+        ];
+
+        let alteracoes = alteracoes_das_operacoes(&pessoas, &operacoes_transacao, &Some("eu".into()));
+        assert_eq!(operacoes(&alteracoes), ["atualizar", "criar", "expurgar", "atualizar"]);
+        assert_eq!(alteracoes[0].anterior.as_ref().unwrap().nome, "Ana");
+        assert_eq!(alteracoes[3].anterior.as_ref().unwrap().nome, "Cid");
+        assert!(alteracoes.iter().all(|a| a.ator.as_deref() == Some("eu")));
+    }
+
+    #[test]
+    fn limpar_expurga_todas_em_ordem_de_id() {
+        let pessoas = [pessoa(2, "Bia"), pessoa(1, "Ana")];
+        let operacoes_transacao = [Operacao::Criar(pessoa(3, "Cid")), Operacao::Limpar];
+
+        let alteracoes = alteracoes_das_operacoes(&pessoas, &operacoes_transacao, &None);
+        assert_eq!(operacoes(&alteracoes), ["criar", "expurgar", "expurgar", "expurgar"]);
+        let ids: Vec<i64> = alteracoes[1..].iter().map(|a| a.id_pessoa).collect();
+        assert_eq!(ids, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{self, BufRead};
+
+use serde_json::Value;
+
+use super::backend::{Entrada, Formato, LinhaInvalida};
+use super::Pessoa;
+
+// ============================================================================
+// JSON LINES
+// Um objeto JSON por linha, um registro por pessoa
+// ============================================================================
+
+/// Formato JSON Lines de pessoas
+///
+/// Campos ausentes no arquivo assumem o valor padrão e campos
+/// desconhecidos são ignorados, então arquivos antigos continuam
+/// legíveis quando a Pessoa ganha novos campos (e vice-versa).
+/// Uma remoção é gravada como `{"id":5,"removido":true}`.
+#[derive(Debug, Clone, Copy)]
+pub struct FormatoJsonl;
+
+impl Formato for FormatoJsonl {
+    fn cabecalho(&self) -> String {
+        String::new()
+    }
+
+    fn codificar(&self, pessoa: &Pessoa) -> String {
+        // Serializar uma struct sem mapas nem floats não tem como falhar
+        let mut linha = serde_json::to_string(pessoa)
+            .expect("Pessoa sempre é serializável em JSON");
+        linha.push('\n');
+        linha
+    }
+
+    fn codificar_remocao(&self, id: i64) -> String {
+        format!("{}\n", serde_json::json!({ "id": id, "removido": true }))
+    }
+
+    fn ler(&self, leitor: Box<dyn BufRead>) -> Box<dyn Iterator<Item = io::Result<Entrada>>> {
+        Box::new(
+            leitor
+                .lines()
+                .enumerate()
+                .filter(|(_, linha)| !matches!(linha, Ok(l) if l.trim().is_empty()))
+                .map(|(i, linha)| Ok(entrada_de_json(i + 1, &linha?))),
+        )
+    }
+}
+
+/// Interpreta uma linha JSON como nova versão, remoção ou linha inválida
+fn entrada_de_json(numero: usize, linha: &str) -> Entrada {
+    let invalida = |motivo: String, aproveitavel: Option<Pessoa>| {
+        Entrada::Invalida(LinhaInvalida {
+            linha: numero,
+            conteudo: linha.to_string(),
+            motivo,
+            aproveitavel,
+        })
+    };
+
+    let valor: Value = match serde_json::from_str(linha) {
+        Ok(valor) => valor,
+        Err(e) => return invalida(format!("JSON inválido: {}", e), None),
+    };
+
+    if valor.get("removido") == Some(&Value::Bool(true)) {
+        if let Some(id) = valor.get("id").and_then(Value::as_i64) {
+            return Entrada::Remocao(id);
+        }
+    }
+
+    match serde_json::from_value::<Pessoa>(valor) {
+        Ok(pessoa) if pessoa.id.is_none() => invalida("ID ausente".into(), Some(pessoa)),
+        Ok(pessoa) => Entrada::Registro(pessoa),
+        Err(e) => invalida(format!("registro inválido: {}", e), None),
+    }
+}
@@ -0,0 +1,280 @@
+use ext_php_rs::prelude::*;
+use std::borrow::Cow;
+
+use super::ValidacaoException;
+
+// ============================================================================
+// TELEFONES BRASILEIROS
+// Análise, validação e formatação de números do plano de numeração nacional
+// ============================================================================
+
+/// DDDs em uso no Brasil (plano de numeração da Anatel)
+const DDDS: [u8; 67] = [
+    11, 12, 13, 14, 15, 16, 17, 18, 19, // SP
+    21, 22, 24, // RJ
+    27, 28, // ES
+    31, 32, 33, 34, 35, 37, 38, // MG
+    41, 42, 43, 44, 45, 46, // PR
+    47, 48, 49, // SC
+    51, 53, 54, 55, // RS
+    61, // DF
+    62, 64, // GO
+    63, // TO
+    65, 66, // MT
+    67, // MS
+    68, // AC
+    69, // RO
+    71, 73, 74, 75, 77, // BA
+    79, // SE
+    81, 87, // PE
+    82, // AL
+    83, // PB
+    84, // RN
+    85, 88, // CE
+    86, 89, // PI
+    91, 93, 94, // PA
+    92, 97, // AM
+    95, // RR
+    96, // AP
+    98, 99, // MA
+];
+
+/// Código do Brasil no plano internacional (E.164)
+const CODIGO_PAIS: &str = "55";
+
+/// Formato de saída de um telefone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatoTelefone {
+    /// Internacional: `+5511987654321`
+    E164,
+
+    /// Nacional: `(11) 98765-4321` ou `(11) 3456-7890`
+    Nacional,
+
+    /// Só dígitos, com DDD: `11987654321`
+    Digitos,
+}
+
+impl FormatoTelefone {
+    /// Interpreta o nome do formato recebido do PHP
+    pub fn de_nome(nome: &str) -> PhpResult<Self> {
+        match nome.to_lowercase().as_str() {
+            "e164" => Ok(Self::E164),
+            "nacional" => Ok(Self::Nacional),
+            "digitos" => Ok(Self::Digitos),
+            _ => Err(ValidacaoException::erro(
+                "formato",
+                format!("Formato de telefone inválido: {} (use e164, nacional ou digitos)", nome)
+            )),
+        }
+    }
+}
+
+/// Telefone brasileiro analisado: DDD e número do assinante
+///
+/// Aceita o número com ou sem formatação, com DDD e, opcionalmente:
+/// - código do país: `+55 11 98765-4321`, `55 11 98765-4321`, `0055...`
+/// - prefixo nacional, com ou sem operadora: `0 11 ...`, `0 21 11 ...`,
+///   inclusive a forma escrita `0xx11 ...` / `(0XX11) ...`
+///
+/// Celulares têm 9 dígitos começando com 9; fixos têm 8 dígitos
+/// começando com 2 a 5
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Telefone {
+    /// DDD (código de área)
+    pub ddd: u8,
+
+    /// Número do assinante, só dígitos
+    pub numero: String,
+}
+
+impl Telefone {
+    /// Analisa um telefone; o erro descreve o primeiro problema encontrado
+    pub fn analisar(telefone: &str) -> Result<Self, String> {
+        let telefone = sem_operadora_escrita(telefone.trim());
+        if telefone.is_empty() {
+            return Err("telefone vazio".into());
+        }
+        if let Some(c) = telefone.chars().find(|&c| !c.is_ascii_digit() && !" +-().".contains(c)) {
+            return Err(format!("caractere inválido: {:?}", c));
+        }
+        if telefone.rfind('+').is_some_and(|posicao| posicao > 0) {
+            return Err("o + só pode aparecer no início".into());
+        }
+
+        let digitos: String = telefone.chars().filter(char::is_ascii_digit).collect();
+        let nacional = if telefone.starts_with('+') {
+            digitos
+                .strip_prefix(CODIGO_PAIS)
+                .ok_or_else(|| "apenas números do Brasil (+55) são aceitos".to_string())?
+        } else {
+            sem_prefixos(&digitos)
+        };
+
+        let (ddd, numero) = match (nacional.len(), nacional.as_bytes().first()) {
+            (10 | 11, _) => nacional.split_at(2),
+            // Celular ou fixo completo, só faltando o DDD
+            (9, Some(b'9')) | (8, Some(b'2'..=b'5')) => return Err("informe o DDD".into()),
+            (quantidade, _) => return Err(format!(
+                "quantidade de dígitos inválida: {} (esperados 10 ou 11, com o DDD)",
+                quantidade
+            )),
+        };
+
+        let ddd: u8 = ddd.parse().map_err(|_| format!("DDD inválido: {}", ddd))?;
+        if !DDDS.contains(&ddd) {
+            return Err(format!("DDD inexistente: {}", ddd));
+        }
+
+        match (numero.len(), numero.as_bytes()[0]) {
+            (9, b'9') | (8, b'2'..=b'5') => {}
+            (9, _) => return Err("celular deve começar com 9".into()),
+            (_, b'6'..=b'9') => return Err("celular deve ter 9 dígitos (com o 9 na frente)".into()),
+            _ => return Err("telefone fixo deve começar com 2, 3, 4 ou 5".into()),
+        }
+
+        Ok(Self {
+            ddd,
+            numero: numero.to_string(),
+        })
+    }
+
+    /// Se é celular (9 dígitos) e não fixo
+    pub fn celular(&self) -> bool {
+        self.numero.len() == 9
+    }
+
+    /// Telefone no formato pedido
+    pub fn formatar(&self, formato: FormatoTelefone) -> String {
+        match formato {
+            FormatoTelefone::E164 => format!("+{}{}{}", CODIGO_PAIS, self.ddd, self.numero),
+            FormatoTelefone::Nacional => {
+                let (inicio, fim) = self.numero.split_at(self.numero.len() - 4);
+                format!("({}) {}-{}", self.ddd, inicio, fim)
+            }
+            FormatoTelefone::Digitos => format!("{}{}", self.ddd, self.numero),
+        }
+    }
+}
+
+/// Remove a forma escrita do prefixo nacional com operadora (`0xx`, em
+/// que `xx` está no lugar do código da operadora), também entre parênteses
+fn sem_operadora_escrita(telefone: &str) -> Cow<'_, str> {
+    let (parenteses, resto) = match telefone.strip_prefix('(') {
+        Some(resto) => ("(", resto),
+        None => ("", telefone),
+    };
+
+    match resto.get(..3) {
+        Some(prefixo) if prefixo.eq_ignore_ascii_case("0xx") => {
+            Cow::Owned(format!("{}{}", parenteses, &resto[3..]))
+        }
+        _ => Cow::Borrowed(telefone),
+    }
+}
+
+/// Remove de um número discado sem `+` o código do país (`55`, `0055`) ou
+/// o prefixo nacional (`0`, com ou sem código de operadora)
+///
+/// Sem prefixos o número tem 10 ou 11 dígitos e nunca começa com 0
+/// (DDDs vão de 11 a 99), o que permite reconhecê-los pelo tamanho
+fn sem_prefixos(digitos: &str) -> &str {
+    if let Some(resto) = digitos.strip_prefix("00").and_then(|resto| resto.strip_prefix(CODIGO_PAIS)) {
+        return resto;
+    }
+    if let Some(resto) = digitos.strip_prefix('0') {
+        return match resto.len() {
+            // 0 + operadora (2 dígitos) + DDD + número
+            12 | 13 => &resto[2..],
+            _ => resto,
+        };
+    }
+    match digitos.len() {
+        12 | 13 => digitos.strip_prefix(CODIGO_PAIS).unwrap_or(digitos),
+        _ => digitos,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ddds_validos_e_unicos() {
+        let unicos: std::collections::HashSet<u8> = DDDS.into_iter().collect();
+        assert_eq!(unicos.len(), DDDS.len());
+        for ddd in DDDS {
+            assert!((11..=99).contains(&ddd) && ddd % 10 != 0, "{}", ddd);
+            assert!(Telefone::analisar(&format!("{}987654321", ddd)).is_ok(), "{}", ddd);
+        }
+    }
+
+    #[test]
+    fn ddds_inexistentes() {
+        for ddd in [20, 23, 25, 26, 29, 30, 36, 39, 40, 50, 52, 56, 57, 58, 59, 60, 70, 72, 76, 78, 80, 90] {
+            assert!(!DDDS.contains(&ddd), "{}", ddd);
+            let erro = Telefone::analisar(&format!("{}987654321", ddd)).unwrap_err();
+            assert!(erro.contains("DDD inexistente"), "{}: {}", ddd, erro);
+        }
+    }
+
+    #[test]
+    fn prefixos_aceitos() {
+        for telefone in [
+            "11987654321",
+            "(11) 98765-4321",
+            "+55 11 98765-4321",
+            "55 11 98765-4321",
+            "0055 11 98765-4321",
+            "0 11 98765-4321",
+            "0 21 11 98765-4321",
+        ] {
+            let analisado = Telefone::analisar(telefone).unwrap();
+            assert_eq!((analisado.ddd, analisado.numero.as_str()), (11, "987654321"), "{}", telefone);
+        }
+    }
+
+    #[test]
+    fn celular_e_fixo() {
+        let celular = Telefone::analisar("11987654321").unwrap();
+        assert!(celular.celular());
+        assert_eq!(celular.formatar(FormatoTelefone::E164), "+5511987654321");
+        assert_eq!(celular.formatar(FormatoTelefone::Nacional), "(11) 98765-4321");
+
+        let fixo = Telefone::analisar("1134567890").unwrap();
+        assert!(!fixo.celular());
+        assert_eq!(fixo.formatar(FormatoTelefone::Nacional), "(11) 3456-7890");
+        assert_eq!(fixo.formatar(FormatoTelefone::Digitos), "1134567890");
+    }
+
+    #[test]
+    fn operadora_escrita_como_xx() {
+        for telefone in ["0xx11 98765-4321", "0XX11 98765-4321", "(0xx11) 98765-4321", "(0XX11) 3456-7890"] {
+            let analisado = Telefone::analisar(telefone).unwrap();
+            assert_eq!(analisado.ddd, 11, "{}", telefone);
+        }
+        assert!(Telefone::analisar("0x11 98765-4321").is_err());
+    }
+
+    #[test]
+    fn mensagens_de_quantidade_de_digitos() {
+        assert_eq!(Telefone::analisar("98765-4321").unwrap_err(), "informe o DDD");
+        assert_eq!(Telefone::analisar("3456-7890").unwrap_err(), "informe o DDD");
+
+        // DDD com número incompleto não é "falta o DDD"
+        let erro = Telefone::analisar("11 2345-678").unwrap_err();
+        assert!(erro.starts_with("quantidade de dígitos inválida: 9"), "{}", erro);
+
+        // A contagem é a do número sem o código do país
+        let erro = Telefone::analisar("+55 11 98765-43210").unwrap_err();
+        assert!(erro.starts_with("quantidade de dígitos inválida: 12"), "{}", erro);
+        assert!(Telefone::analisar("+55").unwrap_err().starts_with("quantidade de dígitos inválida: 0"));
+    }
+
+    #[test]
+    fn numeros_invalidos() {
+        for telefone in ["", "98765-4321", "11 88765-4321", "11 1234-5678", "11 8765-4321", "+1 212 555 0100", "11 9876a-4321", "11+987654321"] {
+            assert!(Telefone::analisar(telefone).is_err(), "{}", telefone);
+        }
+    }
+}
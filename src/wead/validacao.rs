@@ -0,0 +1,422 @@
+use ext_php_rs::prelude::*;
+use ext_php_rs::{boxed::ZBox, types::{ZendClassObject, ZendHashTable}};
+
+use super::documento::Documento;
+use super::email::EnderecoEmail;
+use super::telefone::Telefone;
+use super::{Pessoa, ValidacaoException};
+
+// ============================================================================
+// CLASSE: RegrasValidacao
+// Regras configuráveis aplicadas aos campos de uma pessoa
+// ============================================================================
+
+/// Regra violada por um campo
+#[derive(Debug, Clone)]
+pub struct Violacao {
+    /// Campo (ou parâmetro) inválido
+    pub campo: String,
+
+    /// Regra violada: obrigatorio, tamanho_minimo, tamanho_maximo, formato...
+    pub regra: String,
+
+    /// Mensagem pronta para exibir
+    pub mensagem: String,
+}
+
+impl Violacao {
+    /// Monta a violação de uma regra
+    pub fn new(campo: &str, regra: &str, mensagem: String) -> Self {
+        Self {
+            campo: campo.to_string(),
+            regra: regra.to_string(),
+            mensagem,
+        }
+    }
+
+    /// Representação PHP: ['campo' => ..., 'regra' => ..., 'mensagem' => ...]
+    pub fn para_array(&self) -> PhpResult<ZBox<ZendHashTable>> {
+        let mut array = ZendHashTable::new();
+        array.insert("campo", self.campo.clone())?;
+        array.insert("regra", self.regra.clone())?;
+        array.insert("mensagem", self.mensagem.clone())?;
+        Ok(array)
+    }
+}
+
+/// Formato exigido de um campo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FormatoCampo {
+    /// Endereço de email segundo as RFCs 5322/5321, como em `validar_email()`
+    Email,
+
+    /// Telefone brasileiro com DDD, como em `validar_telefone()`
+    Telefone,
+
+    /// Somente dígitos
+    Numerico,
+
+    /// Letras, espaços, apóstrofos, hífens e pontos (ex.: nomes)
+    Letras,
+
+    /// CPF com dígitos verificadores válidos
+    Cpf,
+
+    /// CNPJ, numérico ou alfanumérico, com dígitos verificadores válidos
+    Cnpj,
+
+    /// CPF ou CNPJ
+    Documento,
+}
+
+impl FormatoCampo {
+    /// Interpreta o nome do formato recebido do PHP
+    fn de_nome(nome: &str) -> PhpResult<Self> {
+        match nome.to_lowercase().as_str() {
+            "email" => Ok(Self::Email),
+            "telefone" => Ok(Self::Telefone),
+            "numerico" => Ok(Self::Numerico),
+            "letras" => Ok(Self::Letras),
+            "cpf" => Ok(Self::Cpf),
+            "cnpj" => Ok(Self::Cnpj),
+            "documento" => Ok(Self::Documento),
+            _ => Err(ValidacaoException::erro(
+                "formato",
+                format!(
+                    "Formato inválido: {} (use email, telefone, numerico, letras, cpf, cnpj ou documento)",
+                    nome
+                )
+            )),
+        }
+    }
+
+    /// Verifica se o valor (já sem espaços nas pontas) está no formato;
+    /// o erro diz o que está errado
+    fn verificar(self, valor: &str) -> Result<(), String> {
+        match self {
+            Self::Email => EnderecoEmail::analisar(valor).map(|_| ()),
+            Self::Telefone => Telefone::analisar(valor).map(|_| ()),
+            Self::Numerico => exigir(valor.chars().all(|c| c.is_ascii_digit()), "use apenas dígitos"),
+            Self::Letras => exigir(
+                valor.chars().all(|c| c.is_alphabetic() || " '-.".contains(c)),
+                "use apenas letras, espaços, apóstrofos, hífens e pontos",
+            ),
+            Self::Cpf => Documento::analisar_cpf(valor).map(|_| ()),
+            Self::Cnpj => Documento::analisar_cnpj(valor).map(|_| ()),
+            Self::Documento => Documento::analisar(valor).map(|_| ()),
+        }
+    }
+}
+
+/// Ok se `aceito`; senão o motivo informado
+fn exigir(aceito: bool, motivo: &str) -> Result<(), String> {
+    if aceito {
+        Ok(())
+    } else {
+        Err(motivo.to_string())
+    }
+}
+
+/// Regras de um campo; valores vazios só são checados por `obrigatorio`
+#[derive(Debug, Clone, Default)]
+struct RegraCampo {
+    /// Se o campo não pode ficar vazio
+    obrigatorio: bool,
+
+    /// Quantidade mínima de caracteres
+    minimo: Option<usize>,
+
+    /// Quantidade máxima de caracteres
+    maximo: Option<usize>,
+
+    /// Formato exigido
+    formato: Option<FormatoCampo>,
+}
+
+impl RegraCampo {
+    /// Acrescenta a `violacoes` as regras que o valor não cumpre
+    fn verificar(&self, campo: &str, rotulo: &str, valor: &str, violacoes: &mut Vec<Violacao>) {
+        let valor = valor.trim();
+
+        if valor.is_empty() {
+            if self.obrigatorio {
+                violacoes.push(Violacao::new(
+                    campo,
+                    "obrigatorio",
+                    format!("{} não pode ser vazio", rotulo),
+                ));
+            }
+            return;
+        }
+
+        let tamanho = valor.chars().count();
+        if let Some(minimo) = self.minimo.filter(|&minimo| tamanho < minimo) {
+            violacoes.push(Violacao::new(
+                campo,
+                "tamanho_minimo",
+                format!("{} deve ter pelo menos {} caracteres", rotulo, minimo),
+            ));
+        }
+        if let Some(maximo) = self.maximo.filter(|&maximo| tamanho > maximo) {
+            violacoes.push(Violacao::new(
+                campo,
+                "tamanho_maximo",
+                format!("{} deve ter no máximo {} caracteres", rotulo, maximo),
+            ));
+        }
+        if let Some(Err(motivo)) = self.formato.map(|formato| formato.verificar(valor)) {
+            violacoes.push(Violacao::new(campo, "formato", format!("{} inválido: {}", rotulo, motivo)));
+        }
+    }
+}
+
+/// Regras de validação de pessoas, usadas por `Pessoa::validar()`,
+/// `Pessoa::errosValidacao()` e pelo Storage (`definirRegrasValidacao()`)
+/// O padrão exige nome, email e telefone, com o email em formato válido,
+/// e aceita pessoas sem documento, mas só com CPF ou CNPJ válido
+/// Equivalente PHP:
+/// ```php
+/// $regras = (new Wead\RegrasValidacao())
+///     ->tamanho('nome', 3, 100)->formato('nome', 'letras')
+///     ->obrigatorio('telefone', false);
+/// foreach ($pessoa->errosValidacao($regras) as $erro) {
+///     $form->erro($erro['campo'], $erro['mensagem']);
+/// }
+/// ```
+#[php_class]
+#[php(name = "Wead\\RegrasValidacao")]
+#[derive(Debug, Clone)]
+pub struct RegrasValidacao {
+    /// Regras do nome
+    nome: RegraCampo,
+
+    /// Regras do email
+    email: RegraCampo,
+
+    /// Regras do telefone
+    telefone: RegraCampo,
+
+    /// Regras do documento (CPF ou CNPJ)
+    documento: RegraCampo,
+}
+
+impl Default for RegrasValidacao {
+    fn default() -> Self {
+        let obrigatorio = RegraCampo {
+            obrigatorio: true,
+            ..RegraCampo::default()
+        };
+
+        Self {
+            nome: obrigatorio.clone(),
+            email: RegraCampo {
+                formato: Some(FormatoCampo::Email),
+                ..obrigatorio.clone()
+            },
+            telefone: obrigatorio,
+            documento: RegraCampo {
+                formato: Some(FormatoCampo::Documento),
+                ..RegraCampo::default()
+            },
+        }
+    }
+}
+
+#[php_impl]
+impl RegrasValidacao {
+    /// Cria as regras padrão
+    pub fn __construct() -> Self {
+        Self::default()
+    }
+
+    /// Define se o campo é obrigatório
+    /// @param string $campo nome, email, telefone ou documento
+    /// @param bool|null $obrigatorio Padrão: true
+    /// @return RegrasValidacao
+    /// @throws ValidacaoException Se o campo for inválido
+    pub fn obrigatorio(
+        self_: &mut ZendClassObject<RegrasValidacao>,
+        campo: String,
+        obrigatorio: Option<bool>,
+    ) -> PhpResult<&mut ZendClassObject<RegrasValidacao>> {
+        self_.regra_mut(&campo)?.obrigatorio = obrigatorio.unwrap_or(true);
+        Ok(self_)
+    }
+
+    /// Limita a quantidade de caracteres do campo (sem contar espaços nas pontas)
+    /// @param string $campo nome, email, telefone ou documento
+    /// @param int|null $minimo null = sem mínimo
+    /// @param int|null $maximo null = sem máximo
+    /// @return RegrasValidacao
+    /// @throws ValidacaoException Se o campo ou os limites forem inválidos
+    pub fn tamanho(
+        self_: &mut ZendClassObject<RegrasValidacao>,
+        campo: String,
+        minimo: Option<i64>,
+        maximo: Option<i64>,
+    ) -> PhpResult<&mut ZendClassObject<RegrasValidacao>> {
+        if minimo.is_some_and(|m| m < 0) || maximo.is_some_and(|m| m < 0) {
+            return Err(ValidacaoException::erro(
+                "tamanho",
+                "Limites de tamanho não podem ser negativos".into()
+            ));
+        }
+        if let (Some(minimo), Some(maximo)) = (minimo, maximo) {
+            if minimo > maximo {
+                return Err(ValidacaoException::erro(
+                    "tamanho",
+                    format!("Tamanho mínimo ({}) maior que o máximo ({})", minimo, maximo)
+                ));
+            }
+        }
+
+        let regra = self_.regra_mut(&campo)?;
+        regra.minimo = minimo.map(|m| m as usize);
+        regra.maximo = maximo.map(|m| m as usize);
+        Ok(self_)
+    }
+
+    /// Exige um formato para o campo
+    /// @param string $campo nome, email, telefone ou documento
+    /// @param string|null $formato email, telefone, numerico, letras, cpf, cnpj,
+    ///   documento (CPF ou CNPJ) ou null (qualquer)
+    /// @return RegrasValidacao
+    /// @throws ValidacaoException Se o campo ou o formato forem inválidos
+    pub fn formato(
+        self_: &mut ZendClassObject<RegrasValidacao>,
+        campo: String,
+        formato: Option<String>,
+    ) -> PhpResult<&mut ZendClassObject<RegrasValidacao>> {
+        let formato = formato.as_deref().map(FormatoCampo::de_nome).transpose()?;
+        self_.regra_mut(&campo)?.formato = formato;
+        Ok(self_)
+    }
+
+    /// Remove todas as regras do campo (qualquer valor passa a ser aceito)
+    /// @param string $campo nome, email, telefone ou documento
+    /// @return RegrasValidacao
+    /// @throws ValidacaoException Se o campo for inválido
+    pub fn liberar(
+        self_: &mut ZendClassObject<RegrasValidacao>,
+        campo: String,
+    ) -> PhpResult<&mut ZendClassObject<RegrasValidacao>> {
+        *self_.regra_mut(&campo)? = RegraCampo::default();
+        Ok(self_)
+    }
+}
+
+impl RegrasValidacao {
+    /// Regras do campo com o nome recebido do PHP
+    fn regra_mut(&mut self, campo: &str) -> PhpResult<&mut RegraCampo> {
+        match campo.to_lowercase().as_str() {
+            "nome" => Ok(&mut self.nome),
+            "email" => Ok(&mut self.email),
+            "telefone" => Ok(&mut self.telefone),
+            "documento" => Ok(&mut self.documento),
+            _ => Err(ValidacaoException::erro(
+                "campo",
+                format!("Campo de validação inválido: {} (use nome, email, telefone ou documento)", campo)
+            )),
+        }
+    }
+
+    /// Todas as regras que a pessoa viola, na ordem dos campos
+    pub fn violacoes(&self, pessoa: &Pessoa) -> Vec<Violacao> {
+        let mut violacoes = Vec::new();
+        self.nome.verificar("nome", "Nome", &pessoa.nome, &mut violacoes);
+        self.email.verificar("email", "Email", &pessoa.email, &mut violacoes);
+        self.telefone.verificar("telefone", "Telefone", &pessoa.telefone, &mut violacoes);
+        let documento = pessoa.documento.as_deref().unwrap_or_default();
+        self.documento.verificar("documento", "Documento", documento, &mut violacoes);
+        violacoes
+    }
+
+    /// Falha com ValidacaoException (com todas as violações) se a pessoa
+    /// não cumprir as regras
+    pub fn verificar(&self, pessoa: &Pessoa) -> PhpResult {
+        let violacoes = self.violacoes(pessoa);
+        if violacoes.is_empty() {
+            return Ok(());
+        }
+        Err(ValidacaoException::de_violacoes(violacoes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pessoa(nome: &str, email: &str, telefone: &str) -> Pessoa {
+        Pessoa::__construct(nome.into(), email.into(), telefone.into())
+    }
+
+    /// Pares (campo, regra) das violações, na ordem
+    fn violadas(regras: &RegrasValidacao, pessoa: &Pessoa) -> Vec<(String, String)> {
+        regras
+            .violacoes(pessoa)
+            .into_iter()
+            .map(|v| (v.campo, v.regra))
+            .collect()
+    }
+
+    fn par(campo: &str, regra: &str) -> (String, String) {
+        (campo.to_string(), regra.to_string())
+    }
+
+    #[test]
+    fn regras_padrao() {
+        let regras = RegrasValidacao::default();
+        assert!(violadas(&regras, &pessoa("Ana", "ana@x.com", "qualquer")).is_empty());
+
+        let mut com_documento = pessoa("Ana", "ana@x.com", "1");
+        com_documento.documento = Some("123".into());
+        assert_eq!(violadas(&regras, &com_documento), [par("documento", "formato")]);
+    }
+
+    #[test]
+    fn todas_as_violacoes_na_ordem_dos_campos() {
+        let regras = RegrasValidacao::default();
+        let violacoes = regras.violacoes(&pessoa("  ", "sem-arroba", ""));
+
+        let pares: Vec<(&str, &str)> = violacoes.iter().map(|v| (v.campo.as_str(), v.regra.as_str())).collect();
+        assert_eq!(pares, [("nome", "obrigatorio"), ("email", "formato"), ("telefone", "obrigatorio")]);
+        assert_eq!(violacoes[0].mensagem, "Nome não pode ser vazio");
+    }
+
+    #[test]
+    fn tamanho_em_caracteres_sem_espacos_nas_pontas() {
+        let mut regras = RegrasValidacao::default();
+        regras.nome.minimo = Some(3);
+        regras.nome.maximo = Some(5);
+
+        assert_eq!(violadas(&regras, &pessoa("  Zé  ", "a@x.com", "1")), [par("nome", "tamanho_minimo")]);
+        assert!(violadas(&regras, &pessoa("João", "a@x.com", "1")).is_empty());
+        assert_eq!(violadas(&regras, &pessoa("Joãozinho", "a@x.com", "1")), [par("nome", "tamanho_maximo")]);
+    }
+
+    #[test]
+    fn vazio_opcional_nao_checa_formato() {
+        let regras = RegrasValidacao {
+            telefone: RegraCampo {
+                formato: Some(FormatoCampo::Telefone),
+                ..RegraCampo::default()
+            },
+            ..RegrasValidacao::default()
+        };
+
+        assert!(violadas(&regras, &pessoa("Ana", "a@x.com", "")).is_empty());
+        assert_eq!(violadas(&regras, &pessoa("Ana", "a@x.com", "123")), [par("telefone", "formato")]);
+        assert!(violadas(&regras, &pessoa("Ana", "a@x.com", "(11) 98765-4321")).is_empty());
+    }
+
+    #[test]
+    fn formatos() {
+        assert!(FormatoCampo::Numerico.verificar("0123").is_ok());
+        assert!(FormatoCampo::Numerico.verificar("12a").is_err());
+        assert!(FormatoCampo::Letras.verificar("Maria D'Ávila-Souza Jr.").is_ok());
+        assert!(FormatoCampo::Letras.verificar("R2D2").is_err());
+        assert!(FormatoCampo::Cpf.verificar("529.982.247-25").is_ok());
+        assert!(FormatoCampo::Cnpj.verificar("529.982.247-25").is_err());
+        assert!(matches!(FormatoCampo::de_nome("CPF"), Ok(FormatoCampo::Cpf)));
+    }
+}